@@ -0,0 +1,73 @@
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// The state shared between clones of a [`MockClock`]
+#[derive(Debug, Default)]
+struct ClockState {
+    /// The time elapsed since the clock was created
+    now: Duration,
+
+    /// Wakers of the futures which pended until the clock reached a later time
+    wakers: Vec<Waker>,
+}
+
+/// A virtual clock for deterministic timing in tests.
+///
+/// Time only moves forward when [`MockClock::advance`] is called. Clones of a `MockClock` share
+/// the same underlying time, so one clone can be given to the mocks or adapters under test while
+/// the test keeps another to advance it. Async operations waiting for a later time are woken
+/// each time the clock is advanced.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::MockClock;
+/// use std::time::Duration;
+///
+/// let clock = MockClock::new();
+/// let shared = clock.clone();
+///
+/// clock.advance(Duration::from_millis(5));
+/// assert_eq!(shared.now(), Duration::from_millis(5));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    /// The shared clock state
+    state: Arc<Mutex<ClockState>>,
+}
+
+impl MockClock {
+    /// Create a new clock, starting at zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the time elapsed since the clock was created
+    pub fn now(&self) -> Duration {
+        self.state.lock().unwrap().now
+    }
+
+    /// Move the clock forward by `d`, waking any async operations waiting for a later time
+    pub fn advance(&self, d: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.now += d;
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// Check whether the clock has reached `at`, registering the task in `cx` to be woken when
+    /// the clock is next advanced if it hasn't
+    pub(crate) fn poll_reached(&self, at: Duration, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.now >= at {
+            return Poll::Ready(());
+        }
+
+        // Only keep one waker for each waiting task, however often it is polled
+        if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
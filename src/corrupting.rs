@@ -0,0 +1,113 @@
+use embedded_io::ErrorType;
+
+/// Describes which bytes a [`Corrupting`] adapter alters. Each position is paired with a mask
+/// which is XORed with the byte at that position, so a single bit can be flipped with a mask
+/// such as `0x01`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorruptionSpec {
+    /// Corrupt the bytes at the given offsets within the data returned by every read
+    PerRead(Vec<(usize, u8)>),
+
+    /// Corrupt the bytes at the given offsets within the whole stream of data read
+    Stream(Vec<(usize, u8)>),
+}
+
+/// An adapter which deterministically corrupts data read from the inner `Read`.
+///
+/// This is intended for testing that checksum or CRC failures are detected. Positions which are
+/// beyond the data returned by a read are left alone.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::{Corrupting, CorruptionSpec, Source};
+/// use embedded_io::Read;
+///
+/// let mut mock_source = Source::new().data([0; 4]).data([0; 4]);
+/// let mut corrupting = Corrupting::new(
+///     mock_source.owned_handle(),
+///     CorruptionSpec::PerRead(vec![(1, 0xff)]),
+/// );
+///
+/// let mut buf: [u8; 4] = [0; 4];
+/// let res = corrupting.read(&mut buf);
+/// assert!(res.is_ok_and(|n| buf[0..n] == [0, 0xff, 0, 0]));
+///
+/// let res = corrupting.read(&mut buf);
+/// assert!(res.is_ok_and(|n| buf[0..n] == [0, 0xff, 0, 0]));
+/// ```
+#[derive(Debug)]
+pub struct Corrupting<T> {
+    /// The wrapped transport
+    inner: T,
+
+    /// The positions to corrupt
+    spec: CorruptionSpec,
+
+    /// The number of bytes read so far
+    position: usize,
+}
+
+impl<T> Corrupting<T> {
+    /// Wrap `inner`, corrupting the data read from it according to `spec`
+    pub fn new(inner: T, spec: CorruptionSpec) -> Self {
+        Self {
+            inner,
+            spec,
+            position: 0,
+        }
+    }
+
+    /// Get a reference to the wrapped transport
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Consume the adapter, returning the wrapped transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Corrupt the data returned by a read
+    fn corrupt(&mut self, data: &mut [u8]) {
+        match &self.spec {
+            CorruptionSpec::PerRead(positions) => {
+                for &(offset, mask) in positions {
+                    if let Some(byte) = data.get_mut(offset) {
+                        *byte ^= mask;
+                    }
+                }
+            }
+            CorruptionSpec::Stream(positions) => {
+                for &(offset, mask) in positions {
+                    if let Some(byte) = offset
+                        .checked_sub(self.position)
+                        .and_then(|i| data.get_mut(i))
+                    {
+                        *byte ^= mask;
+                    }
+                }
+            }
+        }
+        self.position += data.len();
+    }
+}
+
+impl<T: ErrorType> ErrorType for Corrupting<T> {
+    type Error = T::Error;
+}
+
+impl<T: embedded_io::Read> embedded_io::Read for Corrupting<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.read(buf)?;
+        self.corrupt(&mut buf[0..n]);
+        Ok(n)
+    }
+}
+
+impl<T: embedded_io_async::Read> embedded_io_async::Read for Corrupting<T> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.read(buf).await?;
+        self.corrupt(&mut buf[0..n]);
+        Ok(n)
+    }
+}
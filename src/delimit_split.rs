@@ -0,0 +1,104 @@
+use embedded_io::ErrorType;
+
+/// An adapter which splits data read from the inner `Read` at a delimiter byte.
+///
+/// Each read returns data up to and including the next delimiter at most, regardless of how the
+/// inner transport chunks the data. Any data read from the inner transport beyond a delimiter is
+/// buffered and returned by following reads. This is useful for testing line or record parsers
+/// against arbitrary underlying chunking.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::{DelimitSplit, Source};
+/// use embedded_io::Read;
+///
+/// let mut mock_source = Source::new().data("one\ntwo\nth").data("ree\n");
+/// let mut split = DelimitSplit::new(mock_source.owned_handle(), b'\n');
+///
+/// let mut buf: [u8; 64] = [0; 64];
+/// for expected in ["one\n", "two\n", "th", "ree\n"] {
+///     let res = split.read(&mut buf);
+///     assert!(res.is_ok_and(|n| &buf[0..n] == expected.as_bytes()));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DelimitSplit<T> {
+    /// The wrapped transport
+    inner: T,
+
+    /// The byte to split reads at
+    delimiter: u8,
+
+    /// Data read from the inner transport which hasn't been returned yet
+    buffer: Vec<u8>,
+}
+
+impl<T> DelimitSplit<T> {
+    /// Wrap `inner`, splitting the data read from it after each `delimiter` byte
+    pub fn new(inner: T, delimiter: u8) -> Self {
+        Self {
+            inner,
+            delimiter,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Get a reference to the wrapped transport
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Consume the adapter, returning the wrapped transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Get the length of `data` up to and including the first delimiter
+    fn split_len(&self, data: &[u8]) -> usize {
+        data.iter()
+            .position(|&b| b == self.delimiter)
+            .map_or(data.len(), |i| i + 1)
+    }
+
+    /// Return buffered data to the caller, up to the next delimiter
+    fn read_buffered(&mut self, buf: &mut [u8]) -> usize {
+        let n = self.split_len(&self.buffer).min(buf.len());
+        buf[0..n].copy_from_slice(&self.buffer[0..n]);
+        self.buffer.drain(0..n);
+        n
+    }
+
+    /// Truncate data freshly read from the inner transport at the first delimiter, buffering the
+    /// rest
+    fn split_read(&mut self, data: &[u8]) -> usize {
+        let n = self.split_len(data);
+        self.buffer.extend_from_slice(&data[n..]);
+        n
+    }
+}
+
+impl<T: ErrorType> ErrorType for DelimitSplit<T> {
+    type Error = T::Error;
+}
+
+impl<T: embedded_io::Read> embedded_io::Read for DelimitSplit<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if !self.buffer.is_empty() {
+            return Ok(self.read_buffered(buf));
+        }
+
+        let n = self.inner.read(buf)?;
+        Ok(self.split_read(&buf[0..n]))
+    }
+}
+
+impl<T: embedded_io_async::Read> embedded_io_async::Read for DelimitSplit<T> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if !self.buffer.is_empty() {
+            return Ok(self.read_buffered(buf));
+        }
+
+        let n = self.inner.read(buf).await?;
+        Ok(self.split_read(&buf[0..n]))
+    }
+}
@@ -0,0 +1,140 @@
+/// Number of bytes shown on each row of a [`byte_diff`]
+const ROW_LEN: usize = 8;
+
+/// Width of one column of a [`byte_diff`], with three characters per byte
+const COLUMN_WIDTH: usize = ROW_LEN * 3;
+
+/// Number of rows shown either side of each row containing a difference
+const CONTEXT_ROWS: usize = 2;
+
+/// Most regions of differing rows shown before the rest are summarised
+const MAX_REGIONS: usize = 8;
+
+/// Format a diff of `expected` against `actual`, for use in the messages of failing assertions.
+///
+/// The bytes are shown in hex as aligned rows of expected and actual data, each starting with the
+/// offset of its first byte. Mismatched bytes are marked with `^^` on the line below, and bytes
+/// past the end of the shorter of the two are shown as `--`. The first line summarises the
+/// lengths and the offset of the first difference.
+///
+/// Only the rows near each difference are shown, with `...` in place of the rows skipped between
+/// them, so long buffers give a short diff. After the first few regions of differences, the rest
+/// are counted rather than shown.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::byte_diff;
+/// let diff = byte_diff("hello\r\n".as_bytes(), "hello\n".as_bytes());
+/// assert_eq!(
+///     diff,
+///     "expected 7 bytes, actual 6 bytes, first difference at offset 5\n\
+///      \x20 offset  expected                 actual\n\
+///      00000000  68 65 6c 6c 6f 0d 0a     68 65 6c 6c 6f 0a --\n\
+///      \x20                        ^^ ^^                    ^^ ^^"
+/// );
+///
+/// // A single difference in a long buffer only shows the rows around it
+/// let expected = vec![0; 1 << 20];
+/// let mut actual = expected.clone();
+/// actual[1000] = 1;
+/// assert_eq!(byte_diff(&expected, &actual).lines().count(), 10);
+/// ```
+pub fn byte_diff(expected: &[u8], actual: &[u8]) -> String {
+    let Some(first) =
+        (0..expected.len().max(actual.len())).find(|&i| expected.get(i) != actual.get(i))
+    else {
+        return format!(
+            "expected and actual are identical ({} bytes)",
+            expected.len()
+        );
+    };
+
+    let mut out = format!(
+        "expected {} bytes, actual {} bytes, first difference at offset {}\n  offset  {:<w$} actual",
+        expected.len(),
+        actual.len(),
+        first,
+        "expected",
+        w = COLUMN_WIDTH
+    );
+
+    // Group the rows to show into regions around the rows containing differences
+    let len = expected.len().max(actual.len());
+    let rows = len.div_ceil(ROW_LEN);
+    let mut regions: Vec<(usize, usize)> = Vec::new();
+    for row in 0..rows {
+        let end = ((row + 1) * ROW_LEN).min(len);
+        if (row * ROW_LEN..end).all(|i| expected.get(i) == actual.get(i)) {
+            continue;
+        }
+
+        let (first_row, end_row) = (
+            row.saturating_sub(CONTEXT_ROWS),
+            (row + CONTEXT_ROWS + 1).min(rows),
+        );
+        match regions.last_mut() {
+            Some(last) if first_row <= last.1 => last.1 = end_row,
+            _ => regions.push((first_row, end_row)),
+        }
+    }
+
+    let mut shown_to = 0;
+    for &(first_row, end_row) in regions.iter().take(MAX_REGIONS) {
+        if first_row > shown_to {
+            out += "\n...";
+        }
+        for row in first_row..end_row {
+            push_row(&mut out, expected, actual, row * ROW_LEN, len);
+        }
+        shown_to = end_row;
+    }
+
+    if regions.len() > MAX_REGIONS {
+        out += &format!(
+            "\n... {} more regions of differences not shown",
+            regions.len() - MAX_REGIONS
+        );
+    } else if shown_to < rows {
+        out += "\n...";
+    }
+
+    out
+}
+
+/// Append the row of a [`byte_diff`] starting at offset `start` to `out`, followed by markers
+/// under any mismatched bytes
+fn push_row(out: &mut String, expected: &[u8], actual: &[u8], start: usize, len: usize) {
+    let mut expected_col = String::new();
+    let mut actual_col = String::new();
+    let mut markers = String::new();
+    let mut mismatched = false;
+
+    for i in start..(start + ROW_LEN).min(len) {
+        let show = |byte: Option<&u8>| byte.map_or(String::from("--"), |b| format!("{:02x}", b));
+        expected_col += &format!("{} ", show(expected.get(i)));
+        actual_col += &format!("{} ", show(actual.get(i)));
+
+        if expected.get(i) == actual.get(i) {
+            markers += "   ";
+        } else {
+            markers += "^^ ";
+            mismatched = true;
+        }
+    }
+
+    *out += &format!(
+        "\n{:08x}  {:<w$} {}",
+        start,
+        expected_col,
+        actual_col.trim_end(),
+        w = COLUMN_WIDTH
+    );
+    if mismatched {
+        *out += &format!(
+            "\n          {:<w$} {}",
+            markers,
+            markers.trim_end(),
+            w = COLUMN_WIDTH
+        );
+    }
+}
@@ -6,6 +6,7 @@
 //! The main types of interest are:
 //! - [`Source`] : mock object implementing both blocking and async `Read` traits.
 //! - [`Sink`] : mock object implementing both blocking and async `Write` traits.
+//! - [`Loopback`] : mock object where data written to it can be read back, optionally transformed.
 //!
 //! These types can be constructed using the builder-style methods to return a desired sequence of
 //! return values and data. In the case of the `Sink`, the data written to it is stored for later
@@ -49,6 +50,40 @@
 
 use embedded_io::{Error, ErrorKind, ErrorType};
 use std::collections::VecDeque;
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+mod clock;
+mod corrupting;
+mod delimit_split;
+mod diff;
+mod loopback;
+mod pause;
+mod rate_limited;
+mod rng;
+mod script;
+mod shared;
+#[cfg(feature = "stream")]
+mod stream;
+mod tee;
+
+pub use clock::MockClock;
+pub use corrupting::{Corrupting, CorruptionSpec};
+pub use delimit_split::DelimitSplit;
+pub use diff::byte_diff;
+pub use loopback::Loopback;
+pub use pause::PauseController;
+pub use rate_limited::RateLimited;
+pub use rng::Rng;
+pub use script::{ReadOutcome, ReadScript};
+pub use shared::{SharedSink, SharedSource};
+pub use tee::Tee;
+
+/// The error kind returned where a non-blocking transport would return "would block".
+///
+/// `embedded-io` has no `WouldBlock` error kind, as its traits are always blocking, so
+/// `Interrupted` is used to tell the caller that the operation can be retried later.
+const WOULD_BLOCK: ErrorKind = ErrorKind::Interrupted;
 
 /// Error type for the crate. This wraps an [`embedded_io::ErrorKind`].
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -60,6 +95,162 @@ impl Error for MockError {
     }
 }
 
+/// The order in which [`Source::segments`] delivers its segments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeliveryOrder {
+    /// Deliver the segments in ascending sequence number order
+    InOrder,
+
+    /// Deliver the segments in descending sequence number order
+    Reversed,
+
+    /// Deliver the segments at the given indices, where index 0 is the segment with the lowest
+    /// sequence number. Segments may be repeated or omitted.
+    Custom(Vec<usize>),
+}
+
+/// An operation recorded in the history of a [`Source`] or [`Sink`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Op {
+    /// Data of the given length was read or written
+    Data(usize),
+
+    /// An error of the given kind was returned to the caller
+    Error(ErrorKind),
+
+    /// A length of zero was returned to the caller to signal the connection was closed
+    Closed,
+
+    /// The caller flushed the `Sink`
+    Flush,
+
+    /// A device reset was reached, discarding any partially transferred data
+    Reset,
+
+    /// The caller checked whether the mock was ready with `read_ready` or `write_ready`
+    ReadyCheck,
+}
+
+/// A channel receiver feeding a [`Source`], shared between clones of the `Source`
+#[cfg(feature = "tokio")]
+type SharedReceiver = std::sync::Arc<tokio::sync::Mutex<tokio::sync::mpsc::Receiver<Vec<u8>>>>;
+
+/// A function choosing an error to return for a write
+type ErrorFn = dyn Fn(&[u8]) -> Option<MockError> + Send + Sync;
+
+/// A predicate choosing an error to return for a write, set with [`Sink::error_if`]
+#[derive(Clone)]
+struct ErrorPredicate(std::sync::Arc<ErrorFn>);
+
+impl std::fmt::Debug for ErrorPredicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ErrorPredicate").finish_non_exhaustive()
+    }
+}
+
+/// A function giving the most bytes to accept for a write, from the number of unflushed bytes
+type CurveFn = dyn FnMut(usize) -> usize + Send;
+
+/// A backpressure curve, set with [`Sink::backpressure`]
+#[derive(Clone)]
+struct Backpressure(std::sync::Arc<std::sync::Mutex<Box<CurveFn>>>);
+
+impl std::fmt::Debug for Backpressure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Backpressure").finish_non_exhaustive()
+    }
+}
+
+/// A rule for how writes and flushes on a [`Sink`] must be ordered, set with
+/// [`Sink::flush_discipline`]. The `Sink` panics as soon as the rule is broken.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::{Discipline, Sink};
+/// use embedded_io::Write;
+///
+/// let mut mock_sink = Sink::new()
+///                         .accept_data(64)
+///                         .flush_discipline(Discipline::FlushEachWrite);
+///
+/// for message in ["hello", "world"] {
+///     let res = mock_sink.write(message.as_bytes());
+///     assert!(res.is_ok());
+///
+///     let res = mock_sink.flush();
+///     assert!(res.is_ok());
+/// }
+/// ```
+///
+/// ```rust,should_panic
+/// # use mock_embedded_io::{Discipline, Sink};
+/// use embedded_io::Write;
+///
+/// let mut mock_sink = Sink::new()
+///                         .accept_data(64)
+///                         .flush_discipline(Discipline::NoEmptyFlush);
+///
+/// let _ = mock_sink.write("hello".as_bytes());
+/// let _ = mock_sink.flush();
+///
+/// // Panics, as nothing was written since the previous flush
+/// let _ = mock_sink.flush();
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Discipline {
+    /// All writes must happen before the first flush, so a write following a flush panics. This
+    /// checks that a buffering layer batches a whole message before flushing it.
+    WriteThenFlush,
+
+    /// Each write must be followed by a flush before the next write.
+    FlushEachWrite,
+
+    /// Each flush must follow at least one write since the previous flush, so redundant flushes
+    /// panic.
+    NoEmptyFlush,
+}
+
+/// What a [`Sink`] does after seeing the sentinel of [`Sink::accept_until`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AfterSentinel {
+    /// Close the connection, by returning a written length of zero on the next write
+    Close,
+
+    /// Return the given error on the next write
+    Error(MockError),
+}
+
+/// How a read loop ended, checked with [`Source::assert_terminated_by`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Termination {
+    /// The `Source` signalled end of file by returning `Ok(0)` from a closed item
+    Eof,
+
+    /// The `Source` returned an error of the given kind
+    Error(ErrorKind),
+}
+
+/// How often a [`Source`] duplicates the data it delivers, set with
+/// [`Source::with_duplication`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DuplicationSpec {
+    /// The seed for the random choice of which reads are duplicated
+    pub seed: u64,
+
+    /// The probability, from 0.0 to 1.0, that the data returned by each read is delivered again
+    pub probability: f64,
+}
+
+/// Limits on the total I/O performed by a [`Source`], set with [`Source::budget`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Budget {
+    /// The maximum number of reads, or `None` for no limit
+    pub max_ops: Option<usize>,
+
+    /// The maximum number of bytes delivered, or `None` for no limit
+    pub max_bytes: Option<usize>,
+}
+
 /// A value to be yielded by the Source
 #[derive(Debug, Clone)]
 enum ReadItem {
@@ -71,6 +262,40 @@ enum ReadItem {
 
     /// Return a data length of zero to the caller
     Closed,
+
+    /// Report that the `Source` is not ready to read. This is skipped over by reads.
+    NotReady,
+
+    /// Advance the clock of the `Source`, if set, by the given duration before the following item.
+    /// This is skipped over by reads.
+    Delay(Duration),
+
+    /// Mark the start of a named phase of the script. This is skipped over by reads.
+    Phase(String),
+
+    /// Reset the device, discarding the rest of a partially read data item before it. This is
+    /// skipped over by reads.
+    Reset,
+
+    /// Block reads until the clock of the `Source` reaches the given time. Once it has, this is
+    /// skipped over by reads.
+    WaitUntil(Duration),
+
+    /// Yield data to the caller from a memory-mapped file, starting at the given offset
+    #[cfg(feature = "mmap")]
+    Mapped(std::sync::Arc<memmap2::Mmap>, usize),
+}
+
+#[cfg(feature = "stream")]
+impl ReadItem {
+    /// Convert an item from a stream feeding a [`Source`], where the end of the stream closes it
+    fn from_stream_item(item: Option<Result<Vec<u8>, MockError>>) -> Self {
+        match item {
+            Some(Ok(data)) => ReadItem::Data(data),
+            Some(Err(e)) => ReadItem::Error(e),
+            None => ReadItem::Closed,
+        }
+    }
 }
 
 /// A value to be yielded by the Sink
@@ -79,11 +304,25 @@ enum WriteItem {
     /// Accept data written by the caller up to the given length
     AcceptData(usize),
 
+    /// Accept data written by the caller, which must match the given data
+    ExpectData(Vec<u8>),
+
+    /// Accept data written by the caller up to and including the sentinel, then act as `then`.
+    /// `tail` holds the end of the data seen so far, to detect a sentinel split across writes.
+    AcceptUntil {
+        sentinel: Vec<u8>,
+        tail: Vec<u8>,
+        then: AfterSentinel,
+    },
+
     /// Return an error to the caller
     Error(MockError),
 
     /// Close the connection by returning a written length of zero to the caller
     Closed,
+
+    /// Reset the device, discarding any data accepted since the last flush
+    Reset,
 }
 
 /// An owned handle to a [`Source`] or [`Sink`].
@@ -179,12 +418,113 @@ pub struct OwnedHandle<'a, T> {
 /// # }
 /// ```
 ///
+/// ### Blocking and Async Example
+/// A `Source` can be cloned, so a single script can be run through both the blocking and async
+/// traits to check that both code paths behave identically.
+/// ```rust
+/// # use mock_embedded_io::{Source, MockError};
+/// # #[tokio::main]
+/// # async fn main() {
+/// use embedded_io::ErrorKind;
+///
+/// let script = Source::new()
+///                  .data("hello ")
+///                  .data_interrupted("world!", 3, MockError(ErrorKind::BrokenPipe))
+///                  .closed();
+///
+/// let mut blocking_source = script.clone();
+/// let mut blocking_results = Vec::new();
+/// let mut buf: [u8; 4] = [0; 4];
+/// loop {
+///     let res = embedded_io::Read::read(&mut blocking_source, &mut buf);
+///     blocking_results.push(res.map(|n| buf[0..n].to_vec()));
+///     if res == Ok(0) {
+///         break;
+///     }
+/// }
+///
+/// let mut async_source = script.clone();
+/// let mut async_results = Vec::new();
+/// let mut buf: [u8; 4] = [0; 4];
+/// loop {
+///     let res = embedded_io_async::Read::read(&mut async_source, &mut buf).await;
+///     async_results.push(res.map(|n| buf[0..n].to_vec()));
+///     if res == Ok(0) {
+///         break;
+///     }
+/// }
+///
+/// assert_eq!(blocking_results, async_results);
+/// assert!(blocking_source.is_consumed());
+/// assert!(async_source.is_consumed());
+/// # }
+/// ```
+///
 /// [`embedded_io::Read`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Read.html
 /// [`embedded_io_async::Read`]: https://docs.rs/embedded-io-async/latest/embedded_io_async/trait.Read.html
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Source {
     /// A queue of items to return to the caller
     queue: VecDeque<ReadItem>,
+
+    /// A channel to receive further data from once the queue is empty
+    #[cfg(feature = "tokio")]
+    channel: Option<SharedReceiver>,
+
+    /// The length returned by the most recent successful read
+    last_read_len: Option<usize>,
+
+    /// The operations performed on the `Source` so far
+    history: Vec<Op>,
+
+    /// The buffer length of each read requested by the caller
+    requested_reads: Vec<usize>,
+
+    /// If enabled, the data delivered to the caller so far
+    delivered: Option<Vec<u8>>,
+
+    /// Limits on the total I/O performed
+    budget: Budget,
+
+    /// The number of reads performed so far, counted against the budget
+    ops_used: usize,
+
+    /// The number of bytes delivered so far, counted against the budget
+    bytes_used: usize,
+
+    /// Whether any read has been rejected for exceeding the budget
+    budget_exceeded: bool,
+
+    /// If set, reject reads into buffers smaller than this length
+    min_read_len: Option<usize>,
+
+    /// If set, the controller which can pause delivery of reads
+    pause: Option<PauseController>,
+
+    /// If set, the clock advanced by delays between items
+    clock: Option<MockClock>,
+
+    /// If set, the generator and probability used to duplicate the data returned by reads
+    duplication: Option<(Rng, f64)>,
+
+    /// The number of bytes of duplicated data still to be read, which aren't duplicated again
+    duplicate_remaining: usize,
+
+    /// If set, the script producing items once the queue is empty
+    script: Option<script::SharedScript>,
+
+    /// The name of the most recent phase marker reached by reads
+    phase: Option<String>,
+
+    /// The index and unread length of each read which left part of a data item unread
+    split_reads: Vec<(usize, usize)>,
+
+    /// If set, the stream which supplies items once the queue is empty
+    #[cfg(feature = "stream")]
+    stream: Option<stream::SharedStream>,
+
+    /// The index in the queue of the rest of a partially read data item, if any
+    partial: Option<usize>,
 }
 
 impl Source {
@@ -193,141 +533,2777 @@ impl Source {
         Self::default()
     }
 
+    /// Create a Source which delivers `data` then closes. This is a shortcut for
+    /// `Source::new().data(data).closed()`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let mut mock_source = Source::once("hello");
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hello"));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert_eq!(res, Ok(0));
+    /// ```
+    pub fn once<T: Into<Vec<u8>>>(data: T) -> Self {
+        Self::new().data(data).closed()
+    }
+
+    /// Create a Source which delivers the contents of the file at `path`, without loading it into
+    /// memory. This requires the `mmap` feature.
+    ///
+    /// The file is memory-mapped and reads copy directly from the mapping, which is useful for
+    /// replaying very large captured streams. If the file can't be memory-mapped, it is read into
+    /// memory instead.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let path = std::env::temp_dir().join("mock_embedded_io_from_mmap.bin");
+    /// std::fs::write(&path, "hello world!").unwrap();
+    ///
+    /// let mut mock_source = Source::from_mmap(&path).unwrap().closed();
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hello world!"));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert_eq!(res, Ok(0));
+    /// ```
+    #[cfg(feature = "mmap")]
+    pub fn from_mmap<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        let file = std::fs::File::open(&path)?;
+        let mut source = Self::new();
+
+        // SAFETY: The file must not be modified while it is mapped. This is a test fixture, so
+        // it's up to the test not to modify the file while the `Source` exists.
+        match unsafe { memmap2::Mmap::map(&file) } {
+            Ok(map) if map.is_empty() => {}
+            Ok(map) => source
+                .queue
+                .push_back(ReadItem::Mapped(std::sync::Arc::new(map), 0)),
+            Err(_) => {
+                let data = std::fs::read(path)?;
+                if !data.is_empty() {
+                    source.queue.push_back(ReadItem::Data(data));
+                }
+            }
+        }
+        Ok(source)
+    }
+
+    /// Create a Source which is fed by a channel. This requires the `tokio` feature.
+    ///
+    /// Once any items added with the builder methods have been consumed, each read waits for the
+    /// next message from `rx` and delivers it as a data item. When the channel is closed, reads
+    /// return `Ok(0)`. Blocking reads wait using [`blocking_recv`], so must not be made from
+    /// within an async runtime. Clones of the `Source` share the same receiver.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use embedded_io_async::Read;
+    ///
+    /// let (tx, rx) = tokio::sync::mpsc::channel(4);
+    /// let mut mock_source = Source::from_channel(rx);
+    ///
+    /// tokio::spawn(async move {
+    ///     tx.send("hello".as_bytes().to_vec()).await.unwrap();
+    /// });
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read(&mut buf).await;
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hello"));
+    ///
+    /// // The sender has been dropped, closing the channel
+    /// let res = mock_source.read(&mut buf).await;
+    /// assert_eq!(res, Ok(0));
+    /// # }
+    /// ```
+    ///
+    /// [`blocking_recv`]: https://docs.rs/tokio/latest/tokio/sync/mpsc/struct.Receiver.html#method.blocking_recv
+    #[cfg(feature = "tokio")]
+    pub fn from_channel(rx: tokio::sync::mpsc::Receiver<Vec<u8>>) -> Self {
+        Self {
+            channel: Some(std::sync::Arc::new(tokio::sync::Mutex::new(rx))),
+            ..Self::default()
+        }
+    }
+
+    /// Create a Source which is fed by an async stream. This requires the `stream` feature.
+    ///
+    /// Once any items added with the builder methods have been consumed, each async read pulls
+    /// the next item from `stream`. `Ok` items are delivered as data items, `Err` items are
+    /// returned as read errors, and once the stream ends reads return `Ok(0)`. Clones of the
+    /// `Source` share the same stream.
+    ///
+    /// An item taken from the stream is queued in the same poll that takes it, so dropping a read
+    /// future before it completes never consumes an item which wasn't delivered. Blocking reads
+    /// poll the stream once and panic if it has no item ready, and `read_ready` polls it once
+    /// and reports whether it had an item ready.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{MockError, Source};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use embedded_io::ErrorKind;
+    /// use embedded_io_async::Read;
+    /// use futures_core::Stream;
+    /// use std::collections::VecDeque;
+    /// use std::pin::Pin;
+    /// use std::task::{Context, Poll};
+    ///
+    /// // A stream which yields each of its items immediately
+    /// struct Items(VecDeque<Result<Vec<u8>, MockError>>);
+    ///
+    /// impl Stream for Items {
+    ///     type Item = Result<Vec<u8>, MockError>;
+    ///
+    ///     fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    ///         Poll::Ready(self.0.pop_front())
+    ///     }
+    /// }
+    ///
+    /// let items = Items(VecDeque::from([
+    ///     Ok(b"hello".to_vec()),
+    ///     Err(MockError(ErrorKind::TimedOut)),
+    /// ]));
+    /// let mut mock_source = Source::from_stream(items);
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read(&mut buf).await;
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hello"));
+    ///
+    /// let res = mock_source.read(&mut buf).await;
+    /// assert_eq!(res, Err(MockError(ErrorKind::TimedOut)));
+    ///
+    /// let res = mock_source.read(&mut buf).await;
+    /// assert_eq!(res, Ok(0));
+    /// # }
+    /// ```
+    #[cfg(feature = "stream")]
+    pub fn from_stream<S>(stream: S) -> Self
+    where
+        S: futures_core::Stream<Item = Result<Vec<u8>, MockError>> + Send + 'static,
+    {
+        Self {
+            stream: Some(stream::SharedStream::new(stream)),
+            ..Self::default()
+        }
+    }
+
+    /// Create a Source whose items are produced on demand by `script`.
+    ///
+    /// Once any items added with the builder methods have been consumed, each read with nothing
+    /// left to deliver calls [`ReadScript::next_item`] and delivers the item it returns. Clones of
+    /// the `Source` share the same script.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{ReadOutcome, ReadScript, Source};
+    /// use embedded_io::Read;
+    ///
+    /// // Count upwards, then close after three items
+    /// struct Counter(u8);
+    ///
+    /// impl ReadScript for Counter {
+    ///     fn next_item(&mut self) -> ReadOutcome {
+    ///         self.0 += 1;
+    ///         match self.0 {
+    ///             1..=3 => ReadOutcome::Data(vec![self.0]),
+    ///             _ => ReadOutcome::Closed,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut mock_source = Source::from_script_impl(Box::new(Counter(0)));
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read_exact(&mut buf[0..3]);
+    /// assert!(res.is_ok());
+    /// assert_eq!(&buf[0..3], [1, 2, 3]);
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert_eq!(res, Ok(0));
+    /// ```
+    pub fn from_script_impl(script: Box<dyn ReadScript>) -> Self {
+        Self {
+            script: Some(script::SharedScript::new(script)),
+            ..Self::default()
+        }
+    }
+
+    /// Create a Source which delivers the data recorded by `sink` as a single data item.
+    ///
+    /// This is useful for round-trip tests which encode into a [`Sink`] and then decode from a
+    /// `Source`. Further items can be added with the builder methods, for example to terminate the
+    /// data with [`closed`](Self::closed).
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Sink, Source};
+    /// use embedded_io::{Read, Write};
+    ///
+    /// let mut mock_sink = Sink::new().accept_data(64);
+    /// let res = mock_sink.write_all("hello".as_bytes());
+    /// assert!(res.is_ok());
+    ///
+    /// let mut mock_source = Source::from_sink(mock_sink).closed();
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hello"));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert_eq!(res, Ok(0));
+    /// ```
+    pub fn from_sink(sink: Sink) -> Self {
+        let mut source = Self::new();
+        let data = sink.into_inner_data();
+        if !data.is_empty() {
+            source.queue.push_back(ReadItem::Data(data));
+        }
+        source
+    }
+
     /// Add data to the source. This can be returned to the caller either in one chunk or
     /// incrementally - for example if 20 bytes of data are added, the caller could read all 20
     /// bytes in one call, or read 10 bytes twice before the `Source` will return the following
     /// item.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let mut mock_source = Source::new().data("hello world");
+    ///
+    /// // Read into the tail of a buffer which already holds a partial frame
+    /// let mut buf: [u8; 16] = [0; 16];
+    /// buf[0..3].copy_from_slice(b">> ");
+    /// let res = mock_source.read(&mut buf[3..8]);
+    /// assert_eq!(res, Ok(5));
+    /// assert_eq!(&buf[0..8], b">> hello");
+    ///
+    /// let res = mock_source.read(&mut buf[8..]);
+    /// assert_eq!(res, Ok(6));
+    /// assert_eq!(&buf[0..14], b">> hello world");
+    /// assert_eq!(&buf[14..], [0, 0]);
+    /// ```
     pub fn data<T: Into<Vec<u8>>>(mut self, data: T) -> Self {
         self.queue.push_back(ReadItem::Data(data.into()));
         self
     }
 
-    /// Add an error value to the `Source`.
-    pub fn error(mut self, e: MockError) -> Self {
-        self.queue.push_back(ReadItem::Error(e));
+    /// Add data to the `Source`, where the first read of it returns at most `n` bytes regardless of
+    /// the size of the caller's buffer. The rest of the data is delivered by subsequent reads as
+    /// with [`data`](Self::data). This models a transport which under-delivers on one chunk. `n`
+    /// must be non-zero, as an empty first read would look like the end of file.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let mut mock_source = Source::new().data_yield_at_most("hello world", 3);
+    ///
+    /// let mut buf: [u8; 1024] = [0; 1024];
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hel"));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"lo world"));
+    /// ```
+    pub fn data_yield_at_most<T: Into<Vec<u8>>>(mut self, data: T, n: usize) -> Self {
+        assert!(n > 0, "The first read must be allowed a non-zero length");
+
+        let mut first = data.into();
+        let rest = first.split_off(n.min(first.len()));
+        self.queue.push_back(ReadItem::Data(first));
+        if !rest.is_empty() {
+            self.queue.push_back(ReadItem::Data(rest));
+        }
         self
     }
 
-    /// Add a "connection closed" item to the `Source`. When read, this will return `Ok(0)` to the
-    /// caller (which might then result in an error value if they used the [`read_exact`] method
-    /// instead of [`read`]).
+    /// Add sequence-numbered segments to the `Source`, delivered in the given order.
     ///
-    /// [`read`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Read.html#tymethod.read
-    /// [`read_exact`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Read.html#method.read_exact
-    pub fn closed(mut self) -> Self {
-        self.queue.push_back(ReadItem::Closed);
+    /// This is intended for testing reassembly of out-of-order data. Each segment's payload is
+    /// added as its own data item, so it is delivered in a single read given a large enough buffer.
+    /// The sequence numbers only determine the delivery order and are not added to the data, so
+    /// any tagging scheme should be encoded into the payloads by the test.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{DeliveryOrder, Source};
+    /// use embedded_io::Read;
+    ///
+    /// let segments = vec![(0, vec![0, b'a']), (1, vec![1, b'b']), (2, vec![2, b'c'])];
+    /// let mut mock_source = Source::new().segments(segments, DeliveryOrder::Custom(vec![2, 0, 1]));
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == [2, b'c']));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == [0, b'a']));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == [1, b'b']));
+    /// ```
+    pub fn segments(mut self, mut segments: Vec<(u64, Vec<u8>)>, order: DeliveryOrder) -> Self {
+        segments.sort_by_key(|(seq, _)| *seq);
+
+        match order {
+            DeliveryOrder::InOrder => {
+                for (_, payload) in segments {
+                    self.queue.push_back(ReadItem::Data(payload));
+                }
+            }
+            DeliveryOrder::Reversed => {
+                for (_, payload) in segments.into_iter().rev() {
+                    self.queue.push_back(ReadItem::Data(payload));
+                }
+            }
+            DeliveryOrder::Custom(indices) => {
+                for i in indices {
+                    let (_, payload) = segments.get(i).unwrap_or_else(|| {
+                        panic!(
+                            "Segment index {} is out of range for {} segments",
+                            i,
+                            segments.len()
+                        )
+                    });
+                    self.queue.push_back(ReadItem::Data(payload.clone()));
+                }
+            }
+        }
         self
     }
 
-    /// Check if all of the provided items were consumed
-    pub fn is_consumed(&self) -> bool {
-        self.queue.is_empty()
+    /// Add logical frames to the `Source`, each encoded with the framing function `frame`.
+    ///
+    /// Each framed output is added as its own data item, so a decoder reading from the `Source`
+    /// sees data arriving at realistic frame boundaries.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// // Prefix each frame with its length
+    /// let length_prefixed = |payload: &[u8]| {
+    ///     let mut framed = vec![payload.len() as u8];
+    ///     framed.extend_from_slice(payload);
+    ///     framed
+    /// };
+    ///
+    /// let frames = vec![b"one".to_vec(), b"three".to_vec()];
+    /// let mut mock_source = Source::new().framed(frames, length_prefixed);
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"\x03one"));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"\x05three"));
+    /// ```
+    pub fn framed<F: Fn(&[u8]) -> Vec<u8>>(mut self, frames: Vec<Vec<u8>>, frame: F) -> Self {
+        for payload in frames {
+            self.queue.push_back(ReadItem::Data(frame(&payload)));
+        }
+        self
     }
 
-    /// Get an [`OwnedHandle`] containing the `Source`.
-    pub fn owned_handle(&mut self) -> OwnedHandle<Self> {
-        OwnedHandle { inner: self }
+    /// Add data to the `Source`, split into chunks with lengths taken in turn from `sizes`, cycling
+    /// back to the start of `sizes` if more chunks are needed. Each chunk is added as its own data
+    /// item, so reads with large enough buffers return exactly these lengths. A size of zero adds
+    /// an empty chunk, which is read as `Ok(0)` without closing the `Source`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let mut mock_source = Source::new().chunk_sizes("hello world", vec![3, 0, 5]);
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hel"));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert_eq!(res, Ok(0));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"lo wo"));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"rld"));
+    /// assert!(mock_source.is_consumed());
+    /// ```
+    pub fn chunk_sizes<T: Into<Vec<u8>>>(mut self, data: T, sizes: Vec<usize>) -> Self {
+        assert!(
+            sizes.iter().any(|&size| size > 0),
+            "The chunk sizes must include a non-zero size"
+        );
+
+        let data = data.into();
+        let mut rest = &data[..];
+        for &size in sizes.iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (chunk, remainder) = rest.split_at(size.min(rest.len()));
+            self.queue.push_back(ReadItem::Data(chunk.to_vec()));
+            rest = remainder;
+        }
+        self
     }
-}
 
-/// A mock which can act as a data sink.
-///
-/// An instance of the mock can be constructed using the builder-style methods. Each item added by
-/// the builder methods will be returned in-order when data is written to the `Sink`.
-///
-/// Data can then be written to it using the [`embedded_io::Write`] or [`embedded_io_async::Write`]
-/// traits.
-///
-/// ### Blocking Example
-/// ```rust
-/// # use mock_embedded_io::{Sink, MockError};
-/// use embedded_io::Write;
-///
-/// let mut mock_sink = Sink::new()
-///                         .accept_data(12)
-///                         .error(MockError(embedded_io::ErrorKind::BrokenPipe));
-///
-/// let data_bytes = "hello world!".as_bytes();
-/// let res = mock_sink.write_all(data_bytes);
-/// assert!(res.is_ok());
-///
-/// let res = mock_sink.write(data_bytes);
-/// assert!(res.is_err_and(|e| e == MockError(embedded_io::ErrorKind::BrokenPipe)));
-/// ```
-///
-/// ### Async Example
-/// ```rust
-/// # use mock_embedded_io::{Sink, MockError};
+    /// Add data to the `Source`, split into chunks with lengths drawn from a weighted
+    /// distribution, such as a histogram of read sizes observed in a real capture. Each chunk
+    /// length is chosen from `sizes` with probability proportional to the matching entry of
+    /// `weights`, using an [`Rng`] seeded with `seed`, so a given seed always produces the same
+    /// chunks. As with [`chunk_sizes`](Self::chunk_sizes), each chunk is added as its own data item.
+    ///
+    /// The final chunk is cut short if needed, so the total length delivered always equals the
+    /// length of `data`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let data = [0xaa; 1000];
+    /// let mut mock_source = Source::new().data_with_size_distribution(data, &[1, 64, 512], &[1, 8, 1], 3);
+    ///
+    /// let mut total = 0;
+    /// let mut buf: [u8; 1024] = [0; 1024];
+    /// while !mock_source.is_consumed() {
+    ///     let n = mock_source.read(&mut buf).unwrap();
+    ///     assert!([1, 64, 512].contains(&n) || total + n == 1000);
+    ///     total += n;
+    /// }
+    /// assert_eq!(total, 1000);
+    /// ```
+    pub fn data_with_size_distribution<T: Into<Vec<u8>>>(
+        mut self,
+        data: T,
+        sizes: &[usize],
+        weights: &[u32],
+        seed: u64,
+    ) -> Self {
+        assert_eq!(
+            sizes.len(),
+            weights.len(),
+            "Each chunk size must have a matching weight"
+        );
+        assert!(
+            sizes
+                .iter()
+                .zip(weights)
+                .any(|(&size, &weight)| size > 0 && weight > 0),
+            "The distribution must include a non-zero size with a non-zero weight"
+        );
+
+        let total_weight: usize = weights.iter().map(|&w| w as usize).sum();
+        let mut rng = Rng::new(seed);
+        let data = data.into();
+        let mut rest = &data[..];
+        while !rest.is_empty() {
+            // Walk the cumulative weights to find the size the choice falls in
+            let mut choice = rng.range(0..total_weight);
+            let mut size = 0;
+            for (&candidate, &weight) in sizes.iter().zip(weights) {
+                if choice < weight as usize {
+                    size = candidate;
+                    break;
+                }
+                choice -= weight as usize;
+            }
+
+            let (chunk, remainder) = rest.split_at(size.min(rest.len()));
+            self.queue.push_back(ReadItem::Data(chunk.to_vec()));
+            rest = remainder;
+        }
+        self
+    }
+
+    /// Add data to the `Source` which is split into records ending with `marker`.
+    ///
+    /// Each record, up to and including the marker, is added as its own data item and followed by
+    /// an [`ErrorKind::Interrupted`] error, so the read after each record fails once before the
+    /// next record is available. Any data after the last marker is added without a following
+    /// error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Source, MockError};
+    /// use embedded_io::{ErrorKind, Read};
+    ///
+    /// let mut mock_source = Source::new().boundary("one\r\ntwo\r\nthr", "\r\n");
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"one\r\n"));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert_eq!(res, Err(MockError(ErrorKind::Interrupted)));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"two\r\n"));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert_eq!(res, Err(MockError(ErrorKind::Interrupted)));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"thr"));
+    /// assert!(mock_source.is_consumed());
+    /// ```
+    pub fn boundary<T: Into<Vec<u8>>, M: AsRef<[u8]>>(mut self, data: T, marker: M) -> Self {
+        let data = data.into();
+        let marker = marker.as_ref();
+        assert!(!marker.is_empty(), "The boundary marker must not be empty");
+
+        let mut start = 0;
+        let mut i = 0;
+        while i + marker.len() <= data.len() {
+            if data[i..].starts_with(marker) {
+                let end = i + marker.len();
+                self.queue
+                    .push_back(ReadItem::Data(data[start..end].to_vec()));
+                self.queue
+                    .push_back(ReadItem::Error(MockError(WOULD_BLOCK)));
+                start = end;
+                i = end;
+            } else {
+                i += 1;
+            }
+        }
+
+        if start < data.len() {
+            self.queue.push_back(ReadItem::Data(data[start..].to_vec()));
+        }
+        self
+    }
+
+    /// Make the connection reset after the first `n` bytes of the previously added data item.
+    ///
+    /// The first `n` bytes are delivered normally, then the next read returns
+    /// [`ErrorKind::ConnectionReset`]. Any remaining bytes of the data item are discarded. This
+    /// panics if the previously added item is not data.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Source, MockError};
+    /// use embedded_io::{ErrorKind, Read};
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hello world!")
+    ///                           .reset_after(5);
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hello"));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert_eq!(res, Err(MockError(ErrorKind::ConnectionReset)));
+    ///
+    /// // The rest of the data is gone
+    /// assert!(mock_source.is_consumed());
+    /// ```
+    pub fn reset_after(mut self, n: usize) -> Self {
+        match self.queue.back_mut() {
+            Some(ReadItem::Data(data)) => data.truncate(n),
+            _ => panic!("reset_after must follow a data item"),
+        }
+        self.queue
+            .push_back(ReadItem::Error(MockError(ErrorKind::ConnectionReset)));
+        self
+    }
+
+    /// Mark the start of a named phase of the script, such as `handshake` or `transfer`. Phase
+    /// markers don't affect reads, but the name of the phase reached so far is included in the
+    /// panic message if the `Source` is exhausted, and returned by
+    /// [`current_phase`](Self::current_phase).
+    ///
+    /// ### Example
+    /// ```rust,should_panic
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .phase("handshake")
+    ///                           .data("hello")
+    ///                           .phase("transfer")
+    ///                           .data("data");
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let _ = mock_source.read(&mut buf);
+    /// assert_eq!(mock_source.current_phase(), Some("handshake"));
+    ///
+    /// let _ = mock_source.read(&mut buf);
+    /// assert_eq!(mock_source.current_phase(), Some("transfer"));
+    ///
+    /// // Panics, as the Source was exhausted during phase `transfer`
+    /// let _ = mock_source.read(&mut buf);
+    /// ```
+    pub fn phase(mut self, name: &str) -> Self {
+        self.queue.push_back(ReadItem::Phase(name.to_string()));
+        self
+    }
+
+    /// Get the name of the most recent phase marker reached by reads, or `None` if no phase has
+    /// been reached yet.
+    pub fn current_phase(&self) -> Option<&str> {
+        self.phase.as_deref()
+    }
+
+    /// Add data to the `Source`, with more data to follow immediately. This is the same as
+    /// [`data`](Self::data), and is intended to contrast with [`data_last`](Self::data_last).
+    ///
+    /// While the following item is queued, [`read_ready`] returns `true`.
+    ///
+    /// [`read_ready`]: https://docs.rs/embedded-io/latest/embedded_io/trait.ReadReady.html#tymethod.read_ready
+    pub fn data_more<T: Into<Vec<u8>>>(self, data: T) -> Self {
+        self.data(data)
+    }
+
+    /// Add data to the `Source`, which is the last data available for now.
+    ///
+    /// Once the data has been read in full, [`read_ready`] returns `false` until the caller reads
+    /// again, which then returns the following item as normal. Before the data is read in full,
+    /// `read_ready` returns `true`. Error and "connection closed" items are always ready, as they
+    /// are returned immediately.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::{Read, ReadReady};
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data_more("hel")
+    ///                           .data_last("lo")
+    ///                           .closed();
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// assert_eq!(mock_source.read_ready(), Ok(true));
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hel"));
+    ///
+    /// assert_eq!(mock_source.read_ready(), Ok(true));
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"lo"));
+    ///
+    /// assert_eq!(mock_source.read_ready(), Ok(false));
+    /// let res = mock_source.read(&mut buf);
+    /// assert_eq!(res, Ok(0));
+    /// ```
+    ///
+    /// [`read_ready`]: https://docs.rs/embedded-io/latest/embedded_io/trait.ReadReady.html#tymethod.read_ready
+    pub fn data_last<T: Into<Vec<u8>>>(mut self, data: T) -> Self {
+        self.queue.push_back(ReadItem::Data(data.into()));
+        self.queue.push_back(ReadItem::NotReady);
+        self
+    }
+
+    /// Add data to the `Source` which is delivered one byte per read, with `per_byte_delay` of
+    /// virtual time passing before each byte. This models pathologically slow input.
+    ///
+    /// The delay advances the [`MockClock`] set with [`clock`](Self::clock) as each byte is read,
+    /// in both blocking and async modes, so no real time passes. Without a clock, the delay is
+    /// ignored.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{MockClock, Source};
+    /// use embedded_io::Read;
+    /// use std::time::Duration;
+    ///
+    /// let clock = MockClock::new();
+    /// let mut mock_source = Source::new()
+    ///                           .drip([0x12, 0x34, 0x56, 0x78], Duration::from_millis(10))
+    ///                           .clock(clock.clone());
+    ///
+    /// // The 4-byte field is reassembled from single-byte reads
+    /// let mut buf: [u8; 4] = [0; 4];
+    /// let res = mock_source.read_exact(&mut buf);
+    /// assert!(res.is_ok());
+    /// assert_eq!(u32::from_be_bytes(buf), 0x12345678);
+    ///
+    /// assert_eq!(mock_source.requested_reads(), &[4, 3, 2, 1]);
+    /// assert_eq!(clock.now(), Duration::from_millis(40));
+    /// ```
+    pub fn drip<T: Into<Vec<u8>>>(mut self, data: T, per_byte_delay: Duration) -> Self {
+        for byte in data.into() {
+            self.queue.push_back(ReadItem::Delay(per_byte_delay));
+            self.queue.push_back(ReadItem::Data(vec![byte]));
+        }
+        self
+    }
+
+    /// Advance `clock` by the delays added with [`drip`](Self::drip) as they are read, and wait
+    /// for it to reach the times added with [`wait_until`](Self::wait_until).
+    pub fn clock(mut self, clock: MockClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Block reads of the following items until the [`MockClock`] set with
+    /// [`clock`](Self::clock) reaches the time `at`. Until then, blocking reads return
+    /// [`ErrorKind::Interrupted`] and async reads pend until the clock is advanced. The clock must be set by the time the wait is reached. See
+    /// [`clocked_pair`] for an example.
+    pub fn wait_until(mut self, at: Duration) -> Self {
+        self.queue.push_back(ReadItem::WaitUntil(at));
+        self
+    }
+
+    /// Add `count` frames generated by `frame_gen` to the `Source`, each as its own data item.
+    ///
+    /// The frames are generated from an [`Rng`] seeded with `seed`, so the same seed always
+    /// produces the same frames. This is useful for throwing a reproducible stream of varied but
+    /// valid frames at a decoder.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Rng, Source};
+    /// use embedded_io::Read;
+    ///
+    /// // Generate length-prefixed frames with random payloads
+    /// let frame_gen = |rng: &mut Rng| {
+    ///     let mut frame = vec![0; rng.range(1..16) + 1];
+    ///     frame[0] = (frame.len() - 1) as u8;
+    ///     rng.fill_bytes(&mut frame[1..]);
+    ///     frame
+    /// };
+    ///
+    /// let mut mock_source = Source::new().random_frames(1234, 100, frame_gen);
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// for _ in 0..100 {
+    ///     let res = mock_source.read(&mut buf);
+    ///     assert!(res.is_ok_and(|n| n == buf[0] as usize + 1));
+    /// }
+    /// assert!(mock_source.is_consumed());
+    /// ```
+    pub fn random_frames<F: Fn(&mut Rng) -> Vec<u8>>(
+        mut self,
+        seed: u64,
+        count: usize,
+        frame_gen: F,
+    ) -> Self {
+        let mut rng = Rng::new(seed);
+        for _ in 0..count {
+            self.queue.push_back(ReadItem::Data(frame_gen(&mut rng)));
+        }
+        self
+    }
+
+    /// Add an error value to the `Source`.
+    ///
+    /// If the error is reached partway through a [`read_exact`], it is returned to the caller as
+    /// [`ReadExactError::Other`]. This is the same for both the blocking and async traits.
+    ///
+    /// ### Blocking Example
+    /// ```rust
+    /// # use mock_embedded_io::{Source, MockError};
+    /// use embedded_io::{ErrorKind, Read, ReadExactError};
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hel")
+    ///                           .error(MockError(ErrorKind::BrokenPipe));
+    ///
+    /// let mut buf: [u8; 5] = [0; 5];
+    /// let res = mock_source.read_exact(&mut buf);
+    /// assert_eq!(res, Err(ReadExactError::Other(MockError(ErrorKind::BrokenPipe))));
+    /// ```
+    ///
+    /// ### Async Example
+    /// ```rust
+    /// # use mock_embedded_io::{Source, MockError};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use embedded_io::{ErrorKind, ReadExactError};
+    /// use embedded_io_async::Read;
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hel")
+    ///                           .error(MockError(ErrorKind::BrokenPipe));
+    ///
+    /// let mut buf: [u8; 5] = [0; 5];
+    /// let res = mock_source.read_exact(&mut buf).await;
+    /// assert_eq!(res, Err(ReadExactError::Other(MockError(ErrorKind::BrokenPipe))));
+    /// # }
+    /// ```
+    ///
+    /// [`read_exact`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Read.html#method.read_exact
+    /// [`ReadExactError::Other`]: https://docs.rs/embedded-io/latest/embedded_io/enum.ReadExactError.html
+    pub fn error(mut self, e: MockError) -> Self {
+        self.queue.push_back(ReadItem::Error(e));
+        self
+    }
+
+    /// Add data to the `Source` which is interrupted by an error after the first `at` bytes.
+    ///
+    /// This is equivalent to adding the first `at` bytes with [`data`](Self::data), then the
+    /// error, then the remaining bytes, but expresses the intent in a single item. If `at` is
+    /// zero or past the end of the data, the error is returned before or after all of the data
+    /// respectively.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Source, MockError};
+    /// use embedded_io::{ErrorKind, Read};
+    ///
+    /// let data_bytes: Vec<u8> = (0..100).collect();
+    /// let mut mock_source = Source::new()
+    ///                           .data_interrupted(data_bytes.clone(), 50, MockError(ErrorKind::BrokenPipe));
+    ///
+    /// let mut received = Vec::new();
+    /// let mut errors = 0;
+    /// let mut buf: [u8; 64] = [0; 64];
+    ///
+    /// while received.len() < data_bytes.len() {
+    ///     match mock_source.read(&mut buf) {
+    ///         Ok(n) => received.extend_from_slice(&buf[0..n]),
+    ///         Err(e) => {
+    ///             assert_eq!(e, MockError(ErrorKind::BrokenPipe));
+    ///             assert_eq!(received.len(), 50);
+    ///             errors += 1;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(errors, 1);
+    /// assert_eq!(received, data_bytes);
+    /// assert!(mock_source.is_consumed());
+    /// ```
+    pub fn data_interrupted<T: Into<Vec<u8>>>(mut self, data: T, at: usize, e: MockError) -> Self {
+        let mut prefix = data.into();
+        let suffix = prefix.split_off(at.min(prefix.len()));
+
+        // Don't enqueue empty data items, as these would be read as `Ok(0)`
+        if !prefix.is_empty() {
+            self.queue.push_back(ReadItem::Data(prefix));
+        }
+        self.queue.push_back(ReadItem::Error(e));
+        if !suffix.is_empty() {
+            self.queue.push_back(ReadItem::Data(suffix));
+        }
+        self
+    }
+
+    /// Add a single [`ErrorKind::Interrupted`] error to the `Source`, which is consumed by the read
+    /// that returns it.
+    ///
+    /// This models a transient interruption (like `EINTR`) which the caller is expected to retry.
+    /// To test a retry loop, add the interruption before the data the retry should receive.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::{ErrorKind, Read};
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .interrupted_once()
+    ///                           .data("hello");
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let mut attempts = 0;
+    /// let n = loop {
+    ///     attempts += 1;
+    ///     match mock_source.read(&mut buf) {
+    ///         Err(e) if e.0 == ErrorKind::Interrupted => continue,
+    ///         res => break res.unwrap(),
+    ///     }
+    /// };
+    ///
+    /// assert_eq!(attempts, 2);
+    /// assert_eq!(&buf[0..n], b"hello");
+    /// ```
+    pub fn interrupted_once(mut self) -> Self {
+        self.queue
+            .push_back(ReadItem::Error(MockError(ErrorKind::Interrupted)));
+        self
+    }
+
+    /// Add `count` errors of `kind` to the start of the `Source`, before any other items. This
+    /// models a link which fails until it has been trained, such as a serial port detecting the
+    /// baud rate. The errors are always front-loaded, whichever order the builder methods are
+    /// called in.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::{ErrorKind, Read};
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hello")
+    ///                           .warmup_errors(ErrorKind::InvalidData, 3);
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let mut attempts = 0;
+    /// let n = loop {
+    ///     attempts += 1;
+    ///     match mock_source.read(&mut buf) {
+    ///         Err(e) if e.0 == ErrorKind::InvalidData => continue,
+    ///         res => break res.unwrap(),
+    ///     }
+    /// };
+    ///
+    /// assert_eq!(attempts, 4);
+    /// assert_eq!(&buf[0..n], b"hello");
+    /// ```
+    pub fn warmup_errors(mut self, kind: ErrorKind, count: usize) -> Self {
+        for _ in 0..count {
+            self.push_item_front(ReadItem::Error(MockError(kind)));
+        }
+        self
+    }
+
+    /// Add a device reset to the `Source`. When the reset is reached, the rest of any partially
+    /// read data item before it is discarded, and [`Op::Reset`] is recorded in the history. This
+    /// models a hardware reset interrupting a transfer.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hello world")
+    ///                           .device_reset()
+    ///                           .data("fresh");
+    ///
+    /// let mut buf: [u8; 5] = [0; 5];
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hello"));
+    ///
+    /// // The reset discards the rest of the interrupted transfer
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"fresh"));
+    /// assert!(mock_source.is_consumed());
+    /// ```
+    pub fn device_reset(mut self) -> Self {
+        self.queue.push_back(ReadItem::Reset);
+        self
+    }
+
+    /// Add a "connection closed" item to the `Source`. When read, this will return `Ok(0)` to the
+    /// caller (which might then result in an error value if they used the [`read_exact`] method
+    /// instead of [`read`]).
+    ///
+    /// If the item is reached partway through a [`read_exact`], the caller receives
+    /// [`ReadExactError::UnexpectedEof`]. This is the same for both the blocking and async traits.
+    ///
+    /// ### Blocking Example
+    /// ```rust
+    /// # use mock_embedded_io::{Source, MockError};
+    /// use embedded_io::{Read, ReadExactError};
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hel")
+    ///                           .closed();
+    ///
+    /// let mut buf: [u8; 5] = [0; 5];
+    /// let res = mock_source.read_exact(&mut buf);
+    /// assert_eq!(res, Err(ReadExactError::<MockError>::UnexpectedEof));
+    /// ```
+    ///
+    /// ### Async Example
+    /// ```rust
+    /// # use mock_embedded_io::{Source, MockError};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use embedded_io::ReadExactError;
+    /// use embedded_io_async::Read;
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hel")
+    ///                           .closed();
+    ///
+    /// let mut buf: [u8; 5] = [0; 5];
+    /// let res = mock_source.read_exact(&mut buf).await;
+    /// assert_eq!(res, Err(ReadExactError::<MockError>::UnexpectedEof));
+    /// # }
+    /// ```
+    ///
+    /// [`read`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Read.html#tymethod.read
+    /// [`read_exact`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Read.html#method.read_exact
+    /// [`ReadExactError::UnexpectedEof`]: https://docs.rs/embedded-io/latest/embedded_io/enum.ReadExactError.html
+    pub fn closed(mut self) -> Self {
+        self.queue.push_back(ReadItem::Closed);
+        self
+    }
+
+    /// Add a "connection closed" item followed by `data` to the `Source`, modelling a link which
+    /// drops and is then re-established.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hello")
+    ///                           .eof_then("world");
+    ///
+    /// let mut received = Vec::new();
+    /// let mut reconnects = 0;
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// while !mock_source.is_consumed() {
+    ///     match mock_source.read(&mut buf) {
+    ///         Ok(0) => reconnects += 1,
+    ///         Ok(n) => received.extend_from_slice(&buf[0..n]),
+    ///         Err(e) => panic!("{:?}", e),
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(reconnects, 1);
+    /// assert_eq!(received, b"helloworld");
+    /// ```
+    pub fn eof_then<T: Into<Vec<u8>>>(self, data: T) -> Self {
+        self.closed().data(data)
+    }
+
+    /// Check if all of the provided items were consumed
+    pub fn is_consumed(&self) -> bool {
+        self.queue.iter().all(|item| {
+            matches!(
+                item,
+                ReadItem::NotReady
+                    | ReadItem::Delay(_)
+                    | ReadItem::Phase(_)
+                    | ReadItem::Reset
+                    | ReadItem::WaitUntil(_)
+            )
+        })
+    }
+
+    /// Get the number of bytes returned by the most recent successful read, or `None` if no read
+    /// has succeeded yet.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Source, MockError};
+    /// use embedded_io::{ErrorKind, Read};
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hello")
+    ///                           .error(MockError(ErrorKind::BrokenPipe));
+    /// assert_eq!(mock_source.last_read_len(), None);
+    ///
+    /// let mut buf: [u8; 3] = [0; 3];
+    /// let _ = mock_source.read(&mut buf);
+    /// assert_eq!(mock_source.last_read_len(), Some(3));
+    ///
+    /// let _ = mock_source.read(&mut buf);
+    /// assert_eq!(mock_source.last_read_len(), Some(2));
+    ///
+    /// // Errors don't affect the last successful read length
+    /// let _ = mock_source.read(&mut buf);
+    /// assert_eq!(mock_source.last_read_len(), Some(2));
+    /// ```
+    pub fn last_read_len(&self) -> Option<usize> {
+        self.last_read_len
+    }
+
+    /// Get the history of operations performed on the `Source`, in the order they happened.
+    pub fn history(&self) -> &[Op] {
+        &self.history
+    }
+
+    /// Panic if any error has been returned to the caller.
+    ///
+    /// This is useful where errors are added as tripwires which the code under test should never
+    /// reach.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Source, MockError};
+    /// use embedded_io::{ErrorKind, Read};
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hello")
+    ///                           .error(MockError(ErrorKind::BrokenPipe));
+    ///
+    /// let mut buf: [u8; 5] = [0; 5];
+    /// let res = mock_source.read_exact(&mut buf);
+    /// assert!(res.is_ok());
+    ///
+    /// mock_source.assert_no_errors_consumed();
+    /// assert!(!mock_source.is_consumed());
+    /// ```
+    pub fn assert_no_errors_consumed(&self) {
+        assert_no_errors(&self.history, "Source");
+    }
+
+    /// Panic if any read wasn't preceded by a call to [`read_ready`] since the previous read.
+    ///
+    /// This checks that a driver which is meant to poll for readiness never reads blindly.
+    ///
+    /// ### Example
+    /// ```rust,should_panic
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::{Read, ReadReady};
+    ///
+    /// let mut mock_source = Source::new().data("hello").data("world");
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// if mock_source.read_ready().unwrap() {
+    ///     let _ = mock_source.read(&mut buf);
+    /// }
+    /// let _ = mock_source.read(&mut buf);
+    ///
+    /// // Panics, as the second read wasn't preceded by a readiness check
+    /// mock_source.assert_checked_ready_before_read();
+    /// ```
+    ///
+    /// [`read_ready`]: https://docs.rs/embedded-io/latest/embedded_io/trait.ReadReady.html#tymethod.read_ready
+    pub fn assert_checked_ready_before_read(&self) {
+        let reads = self
+            .history
+            .iter()
+            .enumerate()
+            .filter(|(_, op)| !matches!(op, Op::ReadyCheck | Op::Reset))
+            .map(|(i, _)| i);
+        assert_checked_ready(&self.history, reads, "read");
+    }
+
+    /// Panic if the final read from the `Source` didn't end the read loop with `termination`.
+    ///
+    /// This checks that a read loop stopped because of the end of file or error it was expected to
+    /// stop on, rather than stopping early or reading on past it.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Source, Termination};
+    /// use embedded_io::Read;
+    ///
+    /// let mut mock_source = Source::new().data("hello").closed();
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// while let Ok(n) = mock_source.read(&mut buf) {
+    ///     if n == 0 {
+    ///         break;
+    ///     }
+    /// }
+    ///
+    /// mock_source.assert_terminated_by(Termination::Eof);
+    /// ```
+    pub fn assert_terminated_by(&self, termination: Termination) {
+        let expected = match termination {
+            Termination::Eof => Op::Closed,
+            Termination::Error(kind) => Op::Error(kind),
+        };
+        // Readiness checks and resets aren't reads, so can't end the read loop
+        let reads: Vec<(usize, &Op)> = self
+            .history
+            .iter()
+            .enumerate()
+            .filter(|(_, op)| !matches!(op, Op::ReadyCheck | Op::Reset))
+            .collect();
+        let Some(&(_, last)) = reads.last() else {
+            panic!(
+                "The Source was expected to terminate with {:?}, but it was never read",
+                expected
+            );
+        };
+        if *last != expected {
+            let position = reads.iter().position(|(_, op)| **op == expected);
+            match position {
+                Some(i) => panic!(
+                    "The Source terminated with {:?} at operation {}, but was read {} more times afterwards",
+                    expected,
+                    reads[i].0,
+                    reads.len() - 1 - i
+                ),
+                None => panic!(
+                    "The Source was expected to terminate with {:?}, but the final operation was {:?}",
+                    expected, last
+                ),
+            }
+        }
+    }
+
+    /// Panic if the history of operations performed on the `Source` doesn't exactly match
+    /// `expected`, showing both traces side by side from the first divergence. Readiness checks
+    /// appear in the trace as [`Op::ReadyCheck`], and device resets as [`Op::Reset`], so both must
+    /// be included in `expected`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Op, Source, MockError};
+    /// use embedded_io::{ErrorKind, Read};
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hello")
+    ///                           .error(MockError(ErrorKind::BrokenPipe))
+    ///                           .closed();
+    ///
+    /// let mut buf: [u8; 4] = [0; 4];
+    /// while mock_source.read(&mut buf) != Ok(0) {}
+    ///
+    /// mock_source.assert_trace(&[
+    ///     Op::Data(4),
+    ///     Op::Data(1),
+    ///     Op::Error(ErrorKind::BrokenPipe),
+    ///     Op::Closed,
+    /// ]);
+    /// ```
+    pub fn assert_trace(&self, expected: &[Op]) {
+        assert_trace(&self.history, expected, "Source");
+    }
+
+    /// Get the buffer length of each read requested by the caller, in order.
+    pub fn requested_reads(&self) -> &[usize] {
+        &self.requested_reads
+    }
+
+    /// Record the data delivered to the caller, so it can be inspected with
+    /// [`delivered`](Self::delivered).
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data("hello ")
+    ///                           .data("world!")
+    ///                           .record_delivered();
+    ///
+    /// let mut buf: [u8; 4] = [0; 4];
+    /// for _ in 0..3 {
+    ///     let _ = mock_source.read(&mut buf);
+    /// }
+    ///
+    /// assert_eq!(mock_source.delivered(), b"hello worl");
+    /// ```
+    pub fn record_delivered(mut self) -> Self {
+        self.delivered = Some(Vec::new());
+        self
+    }
+
+    /// Get the data delivered to the caller so far. This is empty unless recording was enabled
+    /// with [`record_delivered`](Self::record_delivered).
+    pub fn delivered(&self) -> &[u8] {
+        self.delivered.as_deref().unwrap_or_default()
+    }
+
+    /// Occasionally deliver the data returned by a read a second time, as if the transport
+    /// duplicated it. After each read which returns data, the same bytes are queued to be read
+    /// again with the probability given by `spec`, and duplicated data is not duplicated again. The
+    /// choices are made by an [`Rng`] seeded from the spec, so a given seed always duplicates the
+    /// same reads.
+    ///
+    /// Duplication applies to the bytes returned by each read rather than to whole items. With
+    /// [`segments`](Self::segments), a segment read in a single call is repeated whole, including
+    /// any sequence tag encoded in its payload, but a segment read in several smaller calls only
+    /// has the part returned by one call repeated.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{DuplicationSpec, Source};
+    /// use embedded_io::Read;
+    ///
+    /// let spec = DuplicationSpec { seed: 7, probability: 1.0 };
+    /// let mut mock_source = Source::new().data("hello").with_duplication(spec);
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hello"));
+    ///
+    /// // The data is delivered again
+    /// let res = mock_source.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == b"hello"));
+    /// assert!(mock_source.is_consumed());
+    /// ```
+    pub fn with_duplication(mut self, spec: DuplicationSpec) -> Self {
+        self.duplication = Some((Rng::new(spec.seed), spec.probability));
+        self
+    }
+
+    /// Limit the total number of reads or bytes delivered by the `Source`.
+    ///
+    /// Once the `Source` has performed `max_ops` reads or delivered at least `max_bytes` bytes,
+    /// all further reads return [`ErrorKind::Other`] without consuming any items. This turns an
+    /// accidental infinite read loop into a fast failure rather than a hang. Use
+    /// [`budget_exceeded`](Self::budget_exceeded) to tell these errors apart from scripted ones.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Budget, Source, MockError};
+    /// use embedded_io::{ErrorKind, Read};
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data([0; 1000])
+    ///                           .budget(Budget { max_ops: None, max_bytes: Some(8) });
+    ///
+    /// let mut buf: [u8; 4] = [0; 4];
+    /// assert_eq!(mock_source.read(&mut buf), Ok(4));
+    /// assert_eq!(mock_source.read(&mut buf), Ok(4));
+    /// assert_eq!(mock_source.read(&mut buf), Err(MockError(ErrorKind::Other)));
+    /// assert_eq!(mock_source.read(&mut buf), Err(MockError(ErrorKind::Other)));
+    /// assert!(mock_source.budget_exceeded());
+    /// ```
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Check if any read has been rejected for exceeding the [`budget`](Self::budget)
+    pub fn budget_exceeded(&self) -> bool {
+        self.budget_exceeded
+    }
+
+    /// Reject reads into buffers smaller than `n` bytes, modelling a transport such as DMA which
+    /// requires a minimum block size.
+    ///
+    /// A read into a smaller buffer returns [`ErrorKind::InvalidInput`] without consuming any
+    /// items, unless the next item is a "connection closed" item. A read into a zero length buffer
+    /// still returns `Ok(0)`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Source, MockError};
+    /// use embedded_io::{ErrorKind, Read};
+    ///
+    /// let mut mock_source = Source::new()
+    ///                           .data([0; 64])
+    ///                           .require_min_buffer(16);
+    ///
+    /// let mut buf: [u8; 16] = [0; 16];
+    /// let res = mock_source.read(&mut buf[0..8]);
+    /// assert_eq!(res, Err(MockError(ErrorKind::InvalidInput)));
+    ///
+    /// let res = mock_source.read(&mut buf);
+    /// assert_eq!(res, Ok(16));
+    /// ```
+    pub fn require_min_buffer(mut self, n: usize) -> Self {
+        self.min_read_len = Some(n);
+        self
+    }
+
+    /// Attach a [`PauseController`] which can pause delivery of reads. While paused, async reads
+    /// pend until the controller is resumed, and blocking reads panic.
+    pub fn pause_controller(mut self, controller: PauseController) -> Self {
+        self.pause = Some(controller);
+        self
+    }
+
+    /// Panic if the buffer lengths of the reads requested by the caller don't match `expected`,
+    /// reporting the index of the first mismatch.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let mut mock_source = Source::new().data([0; 1124]);
+    ///
+    /// let mut buf: [u8; 512] = [0; 512];
+    /// let _ = mock_source.read(&mut buf);
+    /// let _ = mock_source.read(&mut buf);
+    /// let _ = mock_source.read(&mut buf[0..100]);
+    ///
+    /// mock_source.assert_read_sizes(&[512, 512, 100]);
+    /// ```
+    pub fn assert_read_sizes(&self, expected: &[usize]) {
+        if let Some(i) = self
+            .requested_reads
+            .iter()
+            .zip(expected)
+            .position(|(actual, expected)| actual != expected)
+        {
+            panic!(
+                "Read {} requested {} bytes, but {} were expected (requested: {:?}, expected: {:?})",
+                i, self.requested_reads[i], expected[i], self.requested_reads, expected
+            );
+        }
+
+        assert_eq!(
+            self.requested_reads.len(),
+            expected.len(),
+            "{} reads were requested, but {} were expected (requested: {:?}, expected: {:?})",
+            self.requested_reads.len(),
+            expected.len(),
+            self.requested_reads,
+            expected
+        );
+    }
+
+    /// Panic if any data item was split across more than one read, reporting the first read which
+    /// left part of an item unread.
+    ///
+    /// This checks that the caller reads with buffers large enough to take each chunk whole, for
+    /// code which is sensitive to chunk boundaries.
+    ///
+    /// ### Example
+    /// ```rust,should_panic
+    /// # use mock_embedded_io::Source;
+    /// use embedded_io::Read;
+    ///
+    /// let mut mock_source = Source::new().data("hello").data("world!");
+    ///
+    /// let mut buf: [u8; 5] = [0; 5];
+    /// let _ = mock_source.read(&mut buf);
+    /// let _ = mock_source.read(&mut buf);
+    ///
+    /// // Panics, as the second read left 1 byte of "world!" unread
+    /// mock_source.assert_chunks_read_whole();
+    /// ```
+    pub fn assert_chunks_read_whole(&self) {
+        if let Some(&(i, unread)) = self.split_reads.first() {
+            panic!(
+                "Read {} requested {} bytes, which split a data item leaving {} bytes unread",
+                i, self.requested_reads[i], unread
+            );
+        }
+    }
+
+    /// Read the whole of the next item and append it to `buf`, returning the number of bytes
+    /// appended. This is a convenience for the common pattern of reassembling a frame in a growable
+    /// buffer. The read is recorded as a single read of the item's length, or of the minimum length
+    /// set with [`require_min_buffer`](Self::require_min_buffer) if that is longer.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// let mut mock_source = Source::new().data("hel").data("lo").closed();
+    ///
+    /// let mut frame = Vec::new();
+    /// while let Ok(n @ 1..) = mock_source.read_appending(&mut frame) {
+    ///     assert!(n <= 3);
+    /// }
+    ///
+    /// assert_eq!(frame, b"hello");
+    /// assert!(mock_source.is_consumed());
+    /// ```
+    ///
+    /// ### Closed Terminator Example
+    /// ```rust
+    /// # use mock_embedded_io::Source;
+    /// let mut mock_source = Source::new().data("hi").closed().require_min_buffer(4);
+    ///
+    /// let mut frame = b"> ".to_vec();
+    /// assert_eq!(mock_source.read_appending(&mut frame), Ok(2));
+    ///
+    /// // The end of file appends nothing
+    /// assert_eq!(mock_source.read_appending(&mut frame), Ok(0));
+    /// assert_eq!(frame, b"> hi");
+    /// ```
+    pub fn read_appending(&mut self, buf: &mut Vec<u8>) -> Result<usize, MockError> {
+        self.skip_markers();
+        self.fetch_next_item();
+
+        let len = match self.queue.front() {
+            Some(ReadItem::Data(data)) => data.len(),
+            #[cfg(feature = "mmap")]
+            Some(ReadItem::Mapped(map, offset)) => map.len() - offset,
+            _ => 0,
+        };
+        let len = len.max(self.min_read_len.unwrap_or(0));
+
+        let start = buf.len();
+        buf.resize(start + len, 0);
+        let res = embedded_io::Read::read(self, &mut buf[start..]);
+        buf.truncate(start + *res.as_ref().unwrap_or(&0));
+        res
+    }
+
+    /// Get an [`OwnedHandle`] containing the `Source`.
+    pub fn owned_handle(&mut self) -> OwnedHandle<'_, Self> {
+        OwnedHandle { inner: self }
+    }
+
+    /// If the queue is empty, fetch the next item from the channel or script feeding the `Source`
+    fn fetch_next_item(&mut self) {
+        #[cfg(feature = "tokio")]
+        if let Some(rx) = self.channel.as_ref().filter(|_| self.queue.is_empty()) {
+            let item = rx.blocking_lock().blocking_recv();
+            self.queue
+                .push_back(item.map_or(ReadItem::Closed, ReadItem::Data));
+        }
+
+        #[cfg(feature = "stream")]
+        if let Some(stream) = self.stream.as_ref().filter(|_| self.queue.is_empty()) {
+            let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+            let std::task::Poll::Ready(item) = stream.poll_next(&mut cx) else {
+                panic!("The caller tried to read data in blocking mode, but the stream feeding the Source had no item ready");
+            };
+            self.queue.push_back(ReadItem::from_stream_item(item));
+        }
+
+        if let Some(script) = self.script.as_ref().filter(|_| self.queue.is_empty()) {
+            self.queue.push_back(match script.next_item() {
+                ReadOutcome::Data(data) => ReadItem::Data(data),
+                ReadOutcome::Error(e) => ReadItem::Error(e),
+                ReadOutcome::Closed => ReadItem::Closed,
+            });
+        }
+    }
+
+    /// Whether reads are still waiting for the clock to reach `at`
+    fn is_waiting(&self, at: Duration) -> bool {
+        self.wait_clock().now() < at
+    }
+
+    /// Get the clock which waits added with [`wait_until`](Self::wait_until) are measured by
+    fn wait_clock(&self) -> &MockClock {
+        self.clock
+            .as_ref()
+            .expect("The Source reached a wait_until, but no clock is set on the Source")
+    }
+
+    /// Remove any items from the front of the queue which don't return anything to the caller
+    fn skip_markers(&mut self) {
+        // A reset discards the rest of a partially read data item before it
+        if let Some(i) = self
+            .partial
+            .filter(|i| matches!(self.queue.get(i + 1), Some(ReadItem::Reset)))
+        {
+            self.queue.remove(i);
+            self.partial = None;
+        }
+
+        loop {
+            match self.queue.front() {
+                Some(
+                    ReadItem::NotReady | ReadItem::Delay(_) | ReadItem::Phase(_) | ReadItem::Reset,
+                ) => (),
+                Some(ReadItem::WaitUntil(at)) if !self.is_waiting(*at) => (),
+                _ => break,
+            }
+
+            match self.pop_item() {
+                Some(ReadItem::Reset) => self.history.push(Op::Reset),
+                Some(ReadItem::Delay(d)) => {
+                    if let Some(clock) = &self.clock {
+                        clock.advance(d);
+                    }
+                }
+                Some(ReadItem::Phase(name)) => self.phase = Some(name),
+                _ => (),
+            }
+        }
+    }
+
+    /// Take the item at the front of the queue, keeping track of any partially read data item
+    fn pop_item(&mut self) -> Option<ReadItem> {
+        self.partial = self.partial.and_then(|i| i.checked_sub(1));
+        self.queue.pop_front()
+    }
+
+    /// Put `item` at the front of the queue, keeping track of any partially read data item
+    fn push_item_front(&mut self, item: ReadItem) {
+        self.partial = self.partial.map(|i| i + 1);
+        self.queue.push_front(item);
+    }
+
+    /// Put the rest of a partially read data item back at the front of the queue
+    fn push_partial(&mut self, item: ReadItem) {
+        self.queue.push_front(item);
+        self.partial = Some(0);
+    }
+}
+
+/// A mock which can act as a data sink.
+///
+/// An instance of the mock can be constructed using the builder-style methods. Each item added by
+/// the builder methods will be returned in-order when data is written to the `Sink`.
+///
+/// Data can then be written to it using the [`embedded_io::Write`] or [`embedded_io_async::Write`]
+/// traits.
+///
+/// ### Blocking Example
+/// ```rust
+/// # use mock_embedded_io::{Sink, MockError};
+/// use embedded_io::Write;
+///
+/// let mut mock_sink = Sink::new()
+///                         .accept_data(12)
+///                         .error(MockError(embedded_io::ErrorKind::BrokenPipe));
+///
+/// let data_bytes = "hello world!".as_bytes();
+/// let res = mock_sink.write_all(data_bytes);
+/// assert!(res.is_ok());
+///
+/// let res = mock_sink.write(data_bytes);
+/// assert!(res.is_err_and(|e| e == MockError(embedded_io::ErrorKind::BrokenPipe)));
+/// ```
+///
+/// ### Async Example
+/// ```rust
+/// # use mock_embedded_io::{Sink, MockError};
+/// # #[tokio::main]
+/// # async fn main() {
+/// use embedded_io_async::Write;
+///
+/// let mut mock_sink = Sink::new()
+///                         .accept_data(12)
+///                         .error(MockError(embedded_io::ErrorKind::BrokenPipe));
+///
+/// let data_bytes = "hello world!".as_bytes();
+/// let res = mock_sink.write_all(data_bytes).await;
+/// assert!(res.is_ok());
+///
+/// let res = mock_sink.write(data_bytes).await;
+/// assert!(res.is_err_and(|e| e == MockError(embedded_io::ErrorKind::BrokenPipe)));
+/// # }
+/// ```
+///
+/// [`embedded_io::Write`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Read.html
+/// [`embedded_io_async::Write`]: https://docs.rs/embedded-io-async/latest/embedded_io_async/trait.Read.html
+#[derive(Debug, Default, Clone)]
+pub struct Sink {
+    /// A queue of items to return to the caller
+    queue: VecDeque<WriteItem>,
+
+    /// A channel to send written data to once the queue is empty
+    #[cfg(feature = "tokio")]
+    channel: Option<tokio::sync::mpsc::Sender<Vec<u8>>>,
+
+    /// The data that has been received from the writer
+    data: Vec<u8>,
+
+    /// If set, panic when the caller attempts a single write larger than this length
+    max_write_len: Option<usize>,
+
+    /// If set, a predicate over each write which can return an error instead of accepting it
+    error_predicate: Option<ErrorPredicate>,
+
+    /// If set, a curve limiting how much of each write is accepted
+    backpressure: Option<Backpressure>,
+
+    /// The length returned by the most recent successful write
+    last_write_len: Option<usize>,
+
+    /// The operations performed on the `Sink` so far
+    history: Vec<Op>,
+
+    /// The number of bytes accepted since the last flush
+    unflushed_len: usize,
+
+    /// The largest number of unflushed bytes at any point so far
+    peak_unflushed_len: usize,
+
+    /// If set, the most unflushed bytes drained by each successful flush
+    flush_drain_len: Option<usize>,
+
+    /// The written bytes held back from the recorded data until a flush drains them
+    staged: Vec<u8>,
+
+    /// If set, the time before which flushes fail
+    flush_not_before: Option<Duration>,
+
+    /// The index in the history of the operation recorded by each call to `write`
+    write_ops: Vec<usize>,
+
+    /// If set, return an error when the unflushed data would exceed this length
+    flush_threshold: Option<usize>,
+
+    /// A queue of results to return from calls to `flush`. Once empty, flushes succeed.
+    flush_queue: VecDeque<Result<(), MockError>>,
+
+    /// If set, the rule for how writes and flushes must be ordered
+    discipline: Option<Discipline>,
+
+    /// If set, the clock used to timestamp writes
+    clock: Option<MockClock>,
+
+    /// The virtual time of each write which accepted data, if a clock is set
+    write_times: Vec<Duration>,
+
+    /// If set, the controller which can pause acceptance of writes
+    pause: Option<PauseController>,
+}
+
+impl Sink {
+    /// Create a new empty Sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a Sink which accepts and records everything written to it. This is a shortcut for
+    /// `Sink::new().accept_data(usize::MAX)`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::unlimited();
+    ///
+    /// let res = mock_sink.write_all(&[0; 4096]);
+    /// assert!(res.is_ok());
+    /// assert_eq!(mock_sink.into_inner_data().len(), 4096);
+    /// ```
+    pub fn unlimited() -> Self {
+        Self::new().accept_data(usize::MAX)
+    }
+
+    /// Create a Sink which forwards written data to a channel. This requires the `tokio` feature.
+    ///
+    /// Once any items added with the builder methods have been consumed, each write is accepted in
+    /// full and sent to `tx` as a single message, as well as being recorded. If the channel is
+    /// closed, writes return `Ok(0)`. Blocking writes wait using [`blocking_send`], so must not be
+    /// made from within an async runtime. Writes rejected by [`error_if`](Self::error_if) are not
+    /// sent, and only the part of a write accepted by [`backpressure`](Self::backpressure) is.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use embedded_io_async::Write;
+    ///
+    /// let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+    /// let mut mock_sink = Sink::to_channel(tx);
+    ///
+    /// let res = mock_sink.write("hello".as_bytes()).await;
+    /// assert_eq!(res, Ok(5));
+    /// assert_eq!(rx.recv().await, Some("hello".as_bytes().to_vec()));
+    ///
+    /// drop(rx);
+    /// let res = mock_sink.write("hello".as_bytes()).await;
+    /// assert_eq!(res, Ok(0));
+    /// # }
+    /// ```
+    ///
+    /// [`blocking_send`]: https://docs.rs/tokio/latest/tokio/sync/mpsc/struct.Sender.html#method.blocking_send
+    #[cfg(feature = "tokio")]
+    pub fn to_channel(tx: tokio::sync::mpsc::Sender<Vec<u8>>) -> Self {
+        Self {
+            channel: Some(tx),
+            ..Self::default()
+        }
+    }
+
+    /// Accept n bytes of data written to the Sink. This can be written by the caller either in one
+    /// chunk or incrementally. A single `write` of a larger buffer only accepts up to the remaining
+    /// length, which is reflected both in the returned length and in the data recorded.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new().accept_data(3);
+    ///
+    /// let res = mock_sink.write("hello world!".as_bytes());
+    /// assert_eq!(res, Ok(3));
+    ///
+    /// let written = mock_sink.into_inner_data();
+    /// assert_eq!(written, "hel".as_bytes());
+    /// ```
+    pub fn accept_data(mut self, n: usize) -> Self {
+        self.queue.push_back(WriteItem::AcceptData(n));
+        self
+    }
+
+    /// Accept data written to the Sink, which must match `data`. The data can be written either in
+    /// one chunk or incrementally, and the Sink will panic if the caller writes anything different.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new().expect_data("hello");
+    ///
+    /// let res = mock_sink.write_all("hel".as_bytes());
+    /// assert!(res.is_ok());
+    ///
+    /// let res = mock_sink.write_all("lo".as_bytes());
+    /// assert!(res.is_ok());
+    /// assert!(mock_sink.is_consumed());
+    /// ```
+    pub fn expect_data<T: Into<Vec<u8>>>(mut self, data: T) -> Self {
+        self.queue.push_back(WriteItem::ExpectData(data.into()));
+        self
+    }
+
+    /// Accept any data written to the Sink until the `sentinel` byte sequence has been written,
+    /// then close or return an error on the next write, according to `then`. The sentinel is
+    /// detected even if it is split across several writes. The write which completes the sentinel
+    /// only accepts data up to the end of it.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{AfterSentinel, Sink};
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new().accept_until("\r\n".as_bytes(), AfterSentinel::Close);
+    ///
+    /// let res = mock_sink.write("hello\r".as_bytes());
+    /// assert_eq!(res, Ok(6));
+    ///
+    /// let res = mock_sink.write("\nworld".as_bytes());
+    /// assert_eq!(res, Ok(1));
+    ///
+    /// let res = mock_sink.write("world".as_bytes());
+    /// assert_eq!(res, Ok(0));
+    /// assert_eq!(mock_sink.into_inner_data(), "hello\r\n".as_bytes());
+    /// ```
+    pub fn accept_until(mut self, sentinel: &[u8], then: AfterSentinel) -> Self {
+        assert!(!sentinel.is_empty(), "The sentinel must not be empty");
+        self.queue.push_back(WriteItem::AcceptUntil {
+            sentinel: sentinel.to_vec(),
+            tail: Vec::new(),
+            then,
+        });
+        self
+    }
+
+    /// Return an error for any write whose buffer `pred` maps to `Some(e)`, instead of accepting
+    /// it. This models a device which rejects writes depending on their content.
+    ///
+    /// The predicate runs on the whole buffer of each write before the queued items are
+    /// considered, so a rejected write doesn't consume or partially accept an
+    /// [`accept_data`](Self::accept_data) item. Calling this again replaces the previous
+    /// predicate.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Sink, MockError};
+    /// use embedded_io::{ErrorKind, Write};
+    ///
+    /// // Reject any command containing the forbidden opcode 0xff
+    /// let mut mock_sink = Sink::new()
+    ///                         .accept_data(64)
+    ///                         .error_if(|buf| {
+    ///                             buf.contains(&0xff).then_some(MockError(ErrorKind::InvalidInput))
+    ///                         });
+    ///
+    /// let res = mock_sink.write(&[0x01, 0xff]);
+    /// assert_eq!(res, Err(MockError(ErrorKind::InvalidInput)));
+    ///
+    /// let res = mock_sink.write(&[0x01, 0x02]);
+    /// assert_eq!(res, Ok(2));
+    /// ```
+    pub fn error_if<F: Fn(&[u8]) -> Option<MockError> + Send + Sync + 'static>(
+        mut self,
+        pred: F,
+    ) -> Self {
+        self.error_predicate = Some(ErrorPredicate(std::sync::Arc::new(pred)));
+        self
+    }
+
+    /// Limit how much of each write is accepted with a backpressure `curve`. The curve is called
+    /// with the number of bytes written since the last flush, and returns the most bytes to accept
+    /// from this write. Only that much of the caller's buffer is offered to the queued items, so
+    /// a limit of zero returns `Ok(0)` without reaching the next item, even if it's an error or
+    /// closure. This models flow control which tightens as a buffer fills and recovers after a
+    /// flush.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// // Model a 16 byte buffer which is emptied by flushing
+    /// let mut mock_sink = Sink::new()
+    ///                         .accept_data(64)
+    ///                         .backpressure(|staged| 16usize.saturating_sub(staged));
+    ///
+    /// assert_eq!(mock_sink.write(&[0; 10]), Ok(10));
+    /// assert_eq!(mock_sink.write(&[0; 10]), Ok(6));
+    /// assert_eq!(mock_sink.write(&[0; 10]), Ok(0));
+    ///
+    /// assert!(mock_sink.flush().is_ok());
+    /// assert_eq!(mock_sink.write(&[0; 10]), Ok(10));
+    /// ```
+    pub fn backpressure<F: FnMut(usize) -> usize + Send + 'static>(mut self, curve: F) -> Self {
+        self.backpressure = Some(Backpressure(std::sync::Arc::new(std::sync::Mutex::new(
+            Box::new(curve),
+        ))));
+        self
+    }
+
+    /// Add a device reset to the `Sink`. When the reset is reached by the next write, any data
+    /// accepted since the last flush is discarded from the recorded data, as if it was lost from
+    /// the device's buffer, and [`Op::Reset`] is recorded in the history.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Op, Sink};
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new()
+    ///                         .accept_data(5)
+    ///                         .accept_data(5)
+    ///                         .device_reset()
+    ///                         .accept_data(5);
+    ///
+    /// let _ = mock_sink.write_all(b"hello");
+    /// let _ = mock_sink.flush();
+    /// let _ = mock_sink.write_all(b"lost!");
+    /// let _ = mock_sink.write_all(b"again");
+    ///
+    /// assert_eq!(mock_sink.history()[3], Op::Reset);
+    /// assert_eq!(mock_sink.into_inner_data(), b"helloagain");
+    /// ```
+    pub fn device_reset(mut self) -> Self {
+        self.queue.push_back(WriteItem::Reset);
+        self
+    }
+
+    /// Add an error value to the `Sink`
+    pub fn error(mut self, e: MockError) -> Self {
+        self.queue.push_back(WriteItem::Error(e));
+        self
+    }
+
+    /// Add a "connection closed" item to the `Sink`. When written, this will return `Ok(0)` to the
+    /// caller (which might then result in an error value if they used the [`write_all`] method
+    /// instead of [`write`]).
+    ///
+    /// [`write`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Write.html#tymethod.write
+    /// [`write_all`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Write.html#method.write_all
+    pub fn closed(mut self) -> Self {
+        self.queue.push_back(WriteItem::Closed);
+        self
+    }
+
+    /// Panic if the caller ever attempts a single `write` with a buffer longer than `n` bytes.
+    ///
+    /// This is a guard rather than an acceptance policy: it is checked before any items are
+    /// consumed, and is useful for transports with a hard per-write limit (for example a fixed
+    /// size HID report), where an oversized write would be rejected by real hardware.
+    ///
+    /// ### Example
+    /// ```rust,should_panic
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new()
+    ///                         .accept_data(128)
+    ///                         .forbid_write_larger_than(64);
+    ///
+    /// // Panics, as the buffer is larger than 64 bytes
+    /// let _ = mock_sink.write(&[0; 65]);
+    /// ```
+    pub fn forbid_write_larger_than(mut self, n: usize) -> Self {
+        self.max_write_len = Some(n);
+        self
+    }
+
+    /// Require the caller to flush before more than `bytes` of unflushed data are written.
+    ///
+    /// A write which would take the unflushed data past the threshold returns
+    /// [`ErrorKind::OutOfMemory`] without consuming any items, modelling a fixed size hardware
+    /// buffer. Calling `flush` resets the unflushed count to zero.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Sink, MockError};
+    /// use embedded_io::{ErrorKind, Write};
+    ///
+    /// let mut mock_sink = Sink::new()
+    ///                         .accept_data(64)
+    ///                         .flush_threshold(16);
+    ///
+    /// assert_eq!(mock_sink.write(&[0; 15]), Ok(15));
+    /// assert_eq!(mock_sink.write(&[0; 2]), Err(MockError(ErrorKind::OutOfMemory)));
+    ///
+    /// assert!(mock_sink.flush().is_ok());
+    /// assert_eq!(mock_sink.write(&[0; 16]), Ok(16));
+    /// ```
+    pub fn flush_threshold(mut self, bytes: usize) -> Self {
+        self.flush_threshold = Some(bytes);
+        self
+    }
+
+    /// Make the next `n` calls to `flush` fail before a following call succeeds, modelling
+    /// hardware which must be flushed repeatedly until its transmit buffer drains.
+    ///
+    /// The failed flushes return [`ErrorKind::Interrupted`]. Calling this more than once queues
+    /// each sequence of failures and success in order. Once all queued flushes are consumed,
+    /// further flushes succeed.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{Sink, MockError};
+    /// use embedded_io::{ErrorKind, Write};
+    ///
+    /// let mut mock_sink = Sink::new().flush_needs_retries(2);
+    ///
+    /// let mut attempts = 1;
+    /// while let Err(e) = mock_sink.flush() {
+    ///     assert_eq!(e, MockError(ErrorKind::Interrupted));
+    ///     attempts += 1;
+    /// }
+    /// assert_eq!(attempts, 3);
+    /// ```
+    pub fn flush_needs_retries(mut self, n: usize) -> Self {
+        for _ in 0..n {
+            self.flush_queue.push_back(Err(MockError(WOULD_BLOCK)));
+        }
+        self.flush_queue.push_back(Ok(()));
+        self
+    }
+
+    /// Make each successful call to `flush` drain at most `n` of the staged bytes, modelling a
+    /// transmit FIFO which empties incrementally. The caller must then flush repeatedly until
+    /// [`staged_len`](Self::staged_len) reaches zero, so `n` must be non-zero.
+    ///
+    /// Written bytes are held back from the recorded data until a flush drains them, so
+    /// [`into_inner_data`](Self::into_inner_data) only includes the bytes drained so far. The
+    /// staged bytes also count against a [`flush_threshold`](Self::flush_threshold).
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new().accept_data(64).flush_drains(4);
+    ///
+    /// let _ = mock_sink.write_all(&[0; 10]);
+    /// assert_eq!(mock_sink.staged_len(), 10);
+    ///
+    /// let mut flushes = 0;
+    /// while mock_sink.staged_len() > 0 {
+    ///     assert!(mock_sink.flush().is_ok());
+    ///     flushes += 1;
+    /// }
+    /// assert_eq!(flushes, 3);
+    /// assert_eq!(mock_sink.into_inner_data(), [0; 10]);
+    /// ```
+    pub fn flush_drains(mut self, n: usize) -> Self {
+        assert!(
+            n > 0,
+            "Each flush must be allowed to drain a non-zero length"
+        );
+        self.flush_drain_len = Some(n);
+        self
+    }
+
+    /// Panic if writes and flushes are not ordered according to `discipline`.
+    ///
+    /// ### Example
+    /// ```rust,should_panic
+    /// # use mock_embedded_io::{Discipline, Sink};
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new()
+    ///                         .accept_data(64)
+    ///                         .flush_discipline(Discipline::WriteThenFlush);
+    ///
+    /// let _ = mock_sink.write_all("hello".as_bytes());
+    /// let _ = mock_sink.flush();
+    ///
+    /// // Panics, as the write should have been batched before the flush
+    /// let _ = mock_sink.write_all("world".as_bytes());
+    /// ```
+    pub fn flush_discipline(mut self, discipline: Discipline) -> Self {
+        self.discipline = Some(discipline);
+        self
+    }
+
+    /// Timestamp each write which accepts data with the current time of `clock`, so the timing
+    /// of writes can be checked with [`assert_written_within`](Self::assert_written_within).
+    pub fn clock(mut self, clock: MockClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Fail flushes until the [`MockClock`] set with [`clock`](Self::clock) reaches the time `at`.
+    /// Until then, blocking flushes return [`ErrorKind::Interrupted`] and async flushes pend until
+    /// the clock is advanced. The clock must be set by the time the `Sink` is flushed. See [`clocked_pair`] for an example.
+    pub fn flush_not_before(mut self, at: Duration) -> Self {
+        self.flush_not_before = Some(at);
+        self
+    }
+
+    /// Attach a [`PauseController`] which can pause acceptance of writes. While paused, async
+    /// writes pend until the controller is resumed, and blocking writes panic.
+    pub fn pause_controller(mut self, controller: PauseController) -> Self {
+        self.pause = Some(controller);
+        self
+    }
+
+    /// Panic if the time between the first and last writes which accepted data is longer than
+    /// `window`. Times are measured in the virtual time of the [`MockClock`] set with
+    /// [`clock`](Self::clock), which must be set before writing.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::{MockClock, Sink};
+    /// use embedded_io::Write;
+    /// use std::time::Duration;
+    ///
+    /// let clock = MockClock::new();
+    /// let mut mock_sink = Sink::new()
+    ///                         .accept_data(64)
+    ///                         .clock(clock.clone());
+    ///
+    /// let _ = mock_sink.write("hello".as_bytes());
+    /// clock.advance(Duration::from_millis(3));
+    /// let _ = mock_sink.write("world".as_bytes());
+    ///
+    /// mock_sink.assert_written_within(Duration::from_millis(5));
+    /// ```
+    pub fn assert_written_within(&self, window: Duration) {
+        assert!(
+            self.clock.is_some(),
+            "The Sink has no clock to timestamp writes with"
+        );
+
+        if let (Some(first), Some(last)) = (self.write_times.first(), self.write_times.last()) {
+            let span = *last - *first;
+            assert!(
+                span <= window,
+                "The writes to the Sink took {:?}, but were expected within {:?}",
+                span,
+                window
+            );
+        }
+    }
+
+    /// Check if all of the provided items were consumed. A device reset which no write has
+    /// reached yet doesn't need to be consumed.
+    pub fn is_consumed(&self) -> bool {
+        self.queue
+            .iter()
+            .all(|item| matches!(item, WriteItem::Reset))
+            && self.flush_queue.is_empty()
+    }
+
+    /// Get the number of bytes accepted by the most recent successful write, or `None` if no
+    /// write has succeeded yet.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new().accept_data(5);
+    /// assert_eq!(mock_sink.last_write_len(), None);
+    ///
+    /// let _ = mock_sink.write(&[0; 3]);
+    /// assert_eq!(mock_sink.last_write_len(), Some(3));
+    ///
+    /// let _ = mock_sink.write(&[0; 3]);
+    /// assert_eq!(mock_sink.last_write_len(), Some(2));
+    /// ```
+    pub fn last_write_len(&self) -> Option<usize> {
+        self.last_write_len
+    }
+
+    /// Panic if `write` was called more than `n` times.
+    ///
+    /// This checks that an encoder coalesces its output into few writes, without depending on
+    /// exactly how many it uses.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new().accept_data(64);
+    ///
+    /// let _ = mock_sink.write_all("hello ".as_bytes());
+    /// let _ = mock_sink.write_all("world".as_bytes());
+    ///
+    /// mock_sink.assert_write_count_at_most(2);
+    /// mock_sink.assert_write_count_at_least(1);
+    /// ```
+    pub fn assert_write_count_at_most(&self, n: usize) {
+        assert!(
+            self.write_ops.len() <= n,
+            "The caller wrote {} times, but at most {} writes were expected",
+            self.write_ops.len(),
+            n
+        );
+    }
+
+    /// Panic if `write` was called fewer than `n` times.
+    pub fn assert_write_count_at_least(&self, n: usize) {
+        assert!(
+            self.write_ops.len() >= n,
+            "The caller wrote {} times, but at least {} writes were expected",
+            self.write_ops.len(),
+            n
+        );
+    }
+
+    /// Panic if the number of flushes between the write at `start_write_index` and the write at
+    /// `end_write_index` isn't `expected`. Writes are numbered from zero in the order they were
+    /// called. `end_write_index` may be the total number of writes, to count the flushes after the
+    /// final write.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new().accept_data(64);
+    ///
+    /// let _ = mock_sink.write(b"one");
+    /// let _ = mock_sink.write(b"two");
+    /// let _ = mock_sink.write(b"three");
+    /// let _ = mock_sink.flush();
+    ///
+    /// // No flushes between the writes, and exactly one after the final write
+    /// mock_sink.assert_flushes_between(0, 2, 0);
+    /// mock_sink.assert_flushes_between(2, 3, 1);
+    /// ```
+    pub fn assert_flushes_between(
+        &self,
+        start_write_index: usize,
+        end_write_index: usize,
+        expected: usize,
+    ) {
+        assert!(
+            start_write_index <= end_write_index && end_write_index <= self.write_ops.len(),
+            "The writes {}..{} are out of range, as the caller wrote {} times",
+            start_write_index,
+            end_write_index,
+            self.write_ops.len()
+        );
+
+        let start = self
+            .write_ops
+            .get(start_write_index)
+            .map_or(self.history.len(), |&i| i + 1);
+        let end = self
+            .write_ops
+            .get(end_write_index)
+            .map_or(self.history.len(), |&i| i);
+        let events = &self.history[start.min(end)..end];
+        let flushes = events.iter().filter(|op| **op == Op::Flush).count();
+
+        assert_eq!(
+            flushes, expected,
+            "The caller flushed {} times between writes {} and {}, but {} flushes were expected (events between the writes: {:?})",
+            flushes, start_write_index, end_write_index, expected, events
+        );
+    }
+
+    /// Get the number of bytes which have been written but not yet drained by flushing.
+    pub fn staged_len(&self) -> usize {
+        self.unflushed_len
+    }
+
+    /// Get the largest number of bytes which were written but not yet flushed at any point so far.
+    pub fn peak_staged(&self) -> usize {
+        self.peak_unflushed_len
+    }
+
+    /// Panic if the number of bytes written but not yet flushed ever reached `n`.
+    ///
+    /// This checks that a buffering layer flushes often enough to keep its in-flight data within a
+    /// fixed bound.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new().accept_data(64);
+    ///
+    /// for _ in 0..4 {
+    ///     let _ = mock_sink.write_all(&[0; 10]);
+    ///     let _ = mock_sink.write_all(&[0; 6]);
+    ///     let _ = mock_sink.flush();
+    /// }
+    ///
+    /// assert_eq!(mock_sink.peak_staged(), 16);
+    /// mock_sink.assert_peak_staged_below(17);
+    /// ```
+    pub fn assert_peak_staged_below(&self, n: usize) {
+        assert!(
+            self.peak_unflushed_len < n,
+            "The Sink had {} bytes of unflushed data at its peak, but this should have stayed below {}",
+            self.peak_unflushed_len,
+            n
+        );
+    }
+
+    /// Get the history of operations performed on the `Sink`, in the order they happened.
+    pub fn history(&self) -> &[Op] {
+        &self.history
+    }
+
+    /// Panic if any error has been returned to the caller.
+    ///
+    /// ### Example
+    /// ```rust,should_panic
+    /// # use mock_embedded_io::{Sink, MockError};
+    /// use embedded_io::{ErrorKind, Write};
+    ///
+    /// let mut mock_sink = Sink::new()
+    ///                         .accept_data(3)
+    ///                         .error(MockError(ErrorKind::BrokenPipe));
+    ///
+    /// // The `Sink` only accepts 3 bytes before returning an error
+    /// let _ = mock_sink.write_all("hello".as_bytes());
+    ///
+    /// mock_sink.assert_no_errors_consumed();
+    /// ```
+    pub fn assert_no_errors_consumed(&self) {
+        assert_no_errors(&self.history, "Sink");
+    }
+
+    /// Panic if any write wasn't preceded by a call to [`write_ready`] since the previous write.
+    ///
+    /// [`write_ready`]: https://docs.rs/embedded-io/latest/embedded_io/trait.WriteReady.html#tymethod.write_ready
+    pub fn assert_checked_ready_before_write(&self) {
+        assert_checked_ready(&self.history, self.write_ops.iter().copied(), "write");
+    }
+
+    /// Get the inner data that has been received from the writer
+    pub fn into_inner_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Write the raw data received from the writer to the file at `path`, replacing any existing
+    /// contents. This is useful for saving what was written as an artifact when a test fails.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new().accept_data(64);
+    /// let _ = mock_sink.write_all("hello".as_bytes());
+    ///
+    /// let path = std::env::temp_dir().join("mock-embedded-io-dump.bin");
+    /// mock_sink.dump_to_file(&path).unwrap();
+    /// assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn dump_to_file<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, &self.data)
+    }
+
+    /// Split the data received from the writer into messages using `decoder`.
+    ///
+    /// The decoder is called repeatedly with a slice of the remaining data. Each time it decodes a
+    /// message, it must advance the slice past the bytes it consumed and return the message. It
+    /// returns `None` if the remaining data doesn't hold a complete message, which ends decoding;
+    /// any trailing partial message is not returned. Decoding also ends once all of the data is
+    /// consumed.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Sink;
+    /// use embedded_io::Write;
+    ///
+    /// let mut mock_sink = Sink::new().accept_data(64);
+    /// let _ = mock_sink.write_all(b"\x03one\x05three");
+    ///
+    /// // Decode length-prefixed messages
+    /// let messages = mock_sink.decode_frames(|data: &mut &[u8]| {
+    ///     let (&len, rest) = data.split_first()?;
+    ///     let message = rest.get(0..len as usize)?.to_vec();
+    ///     *data = &rest[len as usize..];
+    ///     Some(message)
+    /// });
+    /// assert_eq!(messages, vec![b"one".to_vec(), b"three".to_vec()]);
+    /// ```
+    pub fn decode_frames<F: FnMut(&mut &[u8]) -> Option<Vec<u8>>>(
+        &self,
+        mut decoder: F,
+    ) -> Vec<Vec<u8>> {
+        let mut rest = &self.data[..];
+        let mut messages = Vec::new();
+        while !rest.is_empty() {
+            let len = rest.len();
+            let Some(message) = decoder(&mut rest) else {
+                break;
+            };
+            assert!(
+                rest.len() < len,
+                "The decoder returned a message without advancing past any data"
+            );
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// Get an [`OwnedHandle`] containing the `Sink`
+    pub fn owned_handle(&mut self) -> OwnedHandle<'_, Self> {
+        OwnedHandle { inner: self }
+    }
+
+    /// Check whether accepting `n` more bytes would take the unflushed data past the flush
+    /// threshold
+    fn would_overrun_flush_threshold(&self, n: usize) -> bool {
+        self.flush_threshold
+            .is_some_and(|threshold| self.unflushed_len + n > threshold)
+    }
+
+    /// Get the most recent write or flush in the history
+    fn last_write_or_flush(&self) -> Option<&Op> {
+        self.history
+            .iter()
+            .rev()
+            .find(|op| matches!(op, Op::Data(_) | Op::Flush))
+    }
+
+    /// Panic if a write now would break the flush discipline
+    fn check_write_discipline(&self) {
+        match self.discipline {
+            Some(Discipline::WriteThenFlush) => assert!(
+                !self.history.contains(&Op::Flush),
+                "The caller wrote to the Sink after flushing, but all writes should come before the flush"
+            ),
+            Some(Discipline::FlushEachWrite) => assert!(
+                !matches!(self.last_write_or_flush(), Some(Op::Data(_))),
+                "The caller wrote to the Sink twice, but each write should be followed by a flush"
+            ),
+            Some(Discipline::NoEmptyFlush) | None => {}
+        }
+    }
+
+    /// Panic if a flush now would break the flush discipline
+    fn check_flush_discipline(&self) {
+        if self.discipline == Some(Discipline::NoEmptyFlush) {
+            assert!(
+                matches!(self.last_write_or_flush(), Some(Op::Data(_))),
+                "The caller flushed the Sink, but nothing was written since the previous flush"
+            );
+        }
+    }
+}
+
+/// Build a [`Sink`] and [`Source`] pair for testing a client against a canned request/response
+/// exchange with a server.
+///
+/// For each `(request, response)` pair in `exchanges`, the `Sink` expects the client to write
+/// exactly `request` (see [`Sink::expect_data`]) and the `Source` delivers `response` as a single
+/// data item. The assumed interleaving is that the client writes each request in full before
+/// reading its response, but as the `Sink` and `Source` are independent this ordering is not
+/// itself enforced.
+///
+/// ### Example
+/// ```rust
+/// use embedded_io::{Read, Write};
+///
+/// let (mut mock_sink, mut mock_source) = mock_embedded_io::fixture(&[
+///     ("GET a\n", "1\n"),
+///     ("GET b\n", "2\n"),
+/// ]);
+///
+/// let mut buf: [u8; 64] = [0; 64];
+/// for (request, response) in [("GET a\n", "1\n"), ("GET b\n", "2\n")] {
+///     let res = mock_sink.write_all(request.as_bytes());
+///     assert!(res.is_ok());
+///
+///     let res = mock_source.read(&mut buf);
+///     assert!(res.is_ok_and(|n| &buf[0..n] == response.as_bytes()));
+/// }
+///
+/// assert!(mock_sink.is_consumed());
+/// assert!(mock_source.is_consumed());
+/// ```
+pub fn fixture<C: AsRef<[u8]>, S: AsRef<[u8]>>(exchanges: &[(C, S)]) -> (Sink, Source) {
+    let mut sink = Sink::new();
+    let mut source = Source::new();
+    for (request, response) in exchanges {
+        sink = sink.expect_data(request.as_ref());
+        source = source.data(response.as_ref());
+    }
+    (sink, source)
+}
+
+/// Create a [`Source`] and [`Sink`] which share a single [`MockClock`], returned along with it.
+///
+/// All of the timing features of both mocks then run on the same virtual timeline, so one call to
+/// [`MockClock::advance`] drives both. Each mock can still be configured further with the builder
+/// methods.
+///
+/// ### Blocking Example
+/// ```rust
+/// # use mock_embedded_io::{clocked_pair, MockError};
+/// use embedded_io::{ErrorKind, Read, Write};
+/// use std::time::Duration;
+///
+/// let (source, sink, clock) = clocked_pair();
+/// let mut mock_source = source
+///                           .wait_until(Duration::from_millis(10))
+///                           .data("response");
+/// let mut mock_sink = sink
+///                         .accept_data(64)
+///                         .flush_not_before(Duration::from_millis(10));
+///
+/// let _ = mock_sink.write_all("command".as_bytes());
+///
+/// // Both the read and the flush are delayed until 10ms
+/// let mut buf: [u8; 64] = [0; 64];
+/// assert_eq!(mock_source.read(&mut buf), Err(MockError(ErrorKind::Interrupted)));
+/// assert_eq!(mock_sink.flush(), Err(MockError(ErrorKind::Interrupted)));
+///
+/// // A single advance unblocks both
+/// clock.advance(Duration::from_millis(10));
+/// let res = mock_source.read(&mut buf);
+/// assert!(res.is_ok_and(|n| &buf[0..n] == b"response"));
+/// assert!(mock_sink.flush().is_ok());
+/// ```
+///
+/// ### Async Example
+/// ```rust
+/// # use mock_embedded_io::clocked_pair;
 /// # #[tokio::main]
 /// # async fn main() {
-/// use embedded_io_async::Write;
+/// use embedded_io_async::Read;
+/// use std::time::Duration;
 ///
-/// let mut mock_sink = Sink::new()
-///                         .accept_data(12)
-///                         .error(MockError(embedded_io::ErrorKind::BrokenPipe));
+/// let (source, _sink, clock) = clocked_pair();
+/// let mut mock_source = source
+///                           .wait_until(Duration::from_millis(10))
+///                           .data("response");
 ///
-/// let data_bytes = "hello world!".as_bytes();
-/// let res = mock_sink.write_all(data_bytes).await;
-/// assert!(res.is_ok());
+/// let reader = tokio::spawn(async move {
+///     let mut buf: [u8; 64] = [0; 64];
+///     mock_source.read(&mut buf).await
+/// });
 ///
-/// let res = mock_sink.write(data_bytes).await;
-/// assert!(res.is_err_and(|e| e == MockError(embedded_io::ErrorKind::BrokenPipe)));
+/// // The read pends rather than failing until the clock reaches 10ms
+/// tokio::task::yield_now().await;
+/// assert!(!reader.is_finished());
+///
+/// clock.advance(Duration::from_millis(10));
+/// assert_eq!(reader.await.unwrap(), Ok(8));
 /// # }
 /// ```
+pub fn clocked_pair() -> (Source, Sink, MockClock) {
+    let clock = MockClock::new();
+    let source = Source::new().clock(clock.clone());
+    let sink = Sink::new().clock(clock.clone());
+    (source, sink, clock)
+}
+
+/// Panic if `message` doesn't survive a round trip through `encode` and `decode`.
 ///
-/// [`embedded_io::Write`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Read.html
-/// [`embedded_io_async::Write`]: https://docs.rs/embedded-io-async/latest/embedded_io_async/trait.Read.html
-#[derive(Debug, Default)]
-pub struct Sink {
-    /// A queue of items to return to the caller
-    queue: VecDeque<WriteItem>,
+/// The message is encoded into an unlimited [`Sink`], then a [`Source`] is built from the written
+/// data with [`Source::from_sink`], followed by an end of file. The message decoded from the
+/// `Source` must equal the original. For async codecs, use [`assert_round_trip_async`].
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::{assert_round_trip, Sink, Source};
+/// use embedded_io::{Read, Write};
+///
+/// // A length-prefixed string codec
+/// let encode = |sink: &mut Sink, message: &String| {
+///     sink.write_all(&[message.len() as u8]).unwrap();
+///     sink.write_all(message.as_bytes()).unwrap();
+/// };
+/// let decode = |source: &mut Source| {
+///     let mut len = [0; 1];
+///     source.read_exact(&mut len).unwrap();
+///     let mut buf = vec![0; len[0] as usize];
+///     source.read_exact(&mut buf).unwrap();
+///     String::from_utf8(buf).unwrap()
+/// };
+///
+/// assert_round_trip(String::from("hello"), encode, decode);
+/// ```
+pub fn assert_round_trip<M, E, D>(message: M, encode: E, decode: D)
+where
+    M: PartialEq + std::fmt::Debug,
+    E: Fn(&mut Sink, &M),
+    D: Fn(&mut Source) -> M,
+{
+    let mut sink = Sink::unlimited();
+    encode(&mut sink, &message);
+    let encoded = sink.data.clone();
 
-    /// The data that has been received from the writer
-    data: Vec<u8>,
+    let mut source = Source::from_sink(sink).closed();
+    let decoded = decode(&mut source);
+    check_round_trip(&message, &decoded, &encoded);
 }
 
-impl Sink {
-    /// Create a new empty Sink.
-    pub fn new() -> Self {
-        Self::default()
-    }
+/// Panic if `message` doesn't survive a round trip through the async `encode` and `decode`, as
+/// with [`assert_round_trip`].
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::{assert_round_trip_async, Sink, Source};
+/// # #[tokio::main]
+/// # async fn main() {
+/// use embedded_io_async::{Read, Write};
+///
+/// let encode = async |sink: &mut Sink, message: &Vec<u8>| {
+///     sink.write_all(message).await.unwrap();
+/// };
+/// let decode = async |source: &mut Source| {
+///     let mut buf = vec![0; 3];
+///     source.read_exact(&mut buf).await.unwrap();
+///     buf
+/// };
+///
+/// assert_round_trip_async(vec![1, 2, 3], encode, decode).await;
+/// # }
+/// ```
+pub async fn assert_round_trip_async<M, E, D>(message: M, encode: E, decode: D)
+where
+    M: PartialEq + std::fmt::Debug,
+    E: AsyncFn(&mut Sink, &M),
+    D: AsyncFn(&mut Source) -> M,
+{
+    let mut sink = Sink::unlimited();
+    encode(&mut sink, &message).await;
+    let encoded = sink.data.clone();
 
-    /// Accept n bytes of data written to the Sink
-    pub fn accept_data(mut self, n: usize) -> Self {
-        self.queue.push_back(WriteItem::AcceptData(n));
-        self
-    }
+    let mut source = Source::from_sink(sink).closed();
+    let decoded = decode(&mut source).await;
+    check_round_trip(&message, &decoded, &encoded);
+}
 
-    /// Add an error value to the `Sink`
-    pub fn error(mut self, e: MockError) -> Self {
-        self.queue.push_back(WriteItem::Error(e));
-        self
+/// Panic if the `decoded` message doesn't match the `original`, showing the `encoded` data
+fn check_round_trip<M: PartialEq + std::fmt::Debug>(original: &M, decoded: &M, encoded: &[u8]) {
+    assert!(
+        original == decoded,
+        "The decoded message didn't match the original after a round trip (original: {:?}, decoded: {:?}, encoded: {:02x?})",
+        original,
+        decoded,
+        encoded
+    );
+}
+
+/// Panic if the data written to each of the `sinks` is not identical, reporting which sink
+/// diverged from the first and at which offset.
+///
+/// This is useful for testing a layer which writes the same data to several transports.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::{assert_sinks_equal, Sink};
+/// use embedded_io::Write;
+///
+/// let mut sinks = [Sink::new().accept_data(64), Sink::new().accept_data(64)];
+/// for sink in &mut sinks {
+///     let res = sink.write_all(&[1, 2, 3]);
+///     assert!(res.is_ok());
+/// }
+///
+/// assert_sinks_equal(&[&sinks[0], &sinks[1]]);
+/// ```
+pub fn assert_sinks_equal(sinks: &[&Sink]) {
+    assert_sinks_equal_with(sinks, |data| format!("{:02x?}", data));
+}
+
+/// Panic if the data written to each of the `sinks` is not identical, as with
+/// [`assert_sinks_equal`], but showing the data as text in the panic message.
+///
+/// ### Example
+/// ```rust,should_panic
+/// # use mock_embedded_io::{assert_sinks_equal_str, Sink};
+/// use embedded_io::Write;
+///
+/// let mut first = Sink::new().accept_data(64);
+/// let mut second = Sink::new().accept_data(64);
+/// let _ = first.write_all("hello\r\n".as_bytes());
+/// let _ = second.write_all("hello\n".as_bytes());
+///
+/// // Panics, as the second sink diverges at offset 5
+/// assert_sinks_equal_str(&[&first, &second]);
+/// ```
+pub fn assert_sinks_equal_str(sinks: &[&Sink]) {
+    assert_sinks_equal_with(sinks, |data| format!("{:?}", String::from_utf8_lossy(data)));
+}
+
+/// Compare the data written to each sink against the first, using `show` to format the data in
+/// the panic message
+fn assert_sinks_equal_with<F: Fn(&[u8]) -> String>(sinks: &[&Sink], show: F) {
+    let Some((first, rest)) = sinks.split_first() else {
+        return;
+    };
+
+    for (i, sink) in rest.iter().enumerate() {
+        if sink.data == first.data {
+            continue;
+        }
+
+        let offset = first
+            .data
+            .iter()
+            .zip(&sink.data)
+            .position(|(a, b)| a != b)
+            .unwrap_or_else(|| first.data.len().min(sink.data.len()));
+        panic!(
+            "Sink {} diverged from sink 0 at offset {} (sink 0: {}, sink {}: {})\n{}",
+            i + 1,
+            offset,
+            show(&first.data),
+            i + 1,
+            show(&sink.data),
+            byte_diff(&first.data, &sink.data)
+        );
     }
+}
 
-    /// Add a "connection closed" item to the `Sink`. When written, this will return `Ok(0)` to the
-    /// caller (which might then result in an error value if they used the [`write_all`] method
-    /// instead of [`write`]).
-    ///
-    /// [`write`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Write.html#tymethod.write
-    /// [`write_all`]: https://docs.rs/embedded-io/latest/embedded_io/trait.Write.html#method.write_all
-    pub fn closed(mut self) -> Self {
-        self.queue.push_back(WriteItem::Closed);
-        self
+/// Panic if the history doesn't match the expected trace, naming the mock it came from
+fn assert_trace(history: &[Op], expected: &[Op], name: &str) {
+    if history == expected {
+        return;
     }
 
-    /// Check if all of the provided items were consumed
-    pub fn is_consumed(&self) -> bool {
-        self.queue.is_empty()
+    let diverged = history
+        .iter()
+        .zip(expected)
+        .position(|(actual, expected)| actual != expected)
+        .unwrap_or_else(|| history.len().min(expected.len()));
+
+    let show = |op: Option<&Op>| op.map_or(String::from("-"), |op| format!("{:?}", op));
+    let mut rows = String::new();
+    for i in diverged..history.len().max(expected.len()) {
+        rows += &format!(
+            "\n  {:>4}: {:<24} {}",
+            i,
+            show(expected.get(i)),
+            show(history.get(i))
+        );
     }
 
-    /// Get the inner data that has been received from the writer
-    pub fn into_inner_data(self) -> Vec<u8> {
-        self.data
+    panic!(
+        "The {} trace diverged from the expected trace at operation {}\n  {:>4}  {:<24} {}{}",
+        name, diverged, "", "expected", "actual", rows
+    );
+}
+
+/// Panic if any of the operations at the indices `ops` in the history wasn't preceded by a
+/// readiness check since the previous one, naming the kind of operation
+fn assert_checked_ready<I: Iterator<Item = usize>>(history: &[Op], ops: I, name: &str) {
+    let mut previous = 0;
+    for (n, i) in ops.enumerate() {
+        assert!(
+            history[previous..i].contains(&Op::ReadyCheck),
+            "The caller didn't check readiness before {} {} (operation {})",
+            name,
+            n,
+            i
+        );
+        previous = i + 1;
     }
+}
 
-    /// Get an [`OwnedHandle`] containing the `Sink`
-    pub fn owned_handle(&mut self) -> OwnedHandle<Self> {
-        OwnedHandle { inner: self }
+/// Panic if the history contains an error, naming the mock it came from
+fn assert_no_errors(history: &[Op], name: &str) {
+    if let Some((i, op)) = history
+        .iter()
+        .enumerate()
+        .find(|(_, op)| matches!(op, Op::Error(_)))
+    {
+        panic!(
+            "The {} returned an error at operation {}: {:?}",
+            name, i, op
+        );
     }
 }
 
@@ -341,69 +3317,590 @@ impl ErrorType for Sink {
 
 impl embedded_io::Read for Source {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
-        let next_item = self
-            .queue
-            .pop_front()
-            .expect("The caller tried to read data, but the Source is completely consumed");
+        if let Some(pause) = &self.pause {
+            pause.assert_resumed("read");
+        }
+        self.read_item(buf)
+    }
+}
+
+impl Source {
+    /// Read the next item into `buf`, once any pause has been checked
+    fn read_item(&mut self, buf: &mut [u8]) -> Result<usize, MockError> {
+        let ops_exceeded = self.budget.max_ops.is_some_and(|max| self.ops_used >= max);
+        let bytes_exceeded = self
+            .budget
+            .max_bytes
+            .is_some_and(|max| self.bytes_used >= max);
+        if ops_exceeded || bytes_exceeded {
+            self.budget_exceeded = true;
+            let e = MockError(ErrorKind::Other);
+            self.history.push(Op::Error(e.kind()));
+            return Err(e);
+        }
+        self.ops_used += 1;
+        self.skip_markers();
+        self.fetch_next_item();
+
+        self.requested_reads.push(buf.len());
+
+        if let Some(ReadItem::WaitUntil(_)) = self.queue.front() {
+            let e = MockError(WOULD_BLOCK);
+            self.history.push(Op::Error(e.kind()));
+            return Err(e);
+        }
+
+        if !buf.is_empty()
+            && self.min_read_len.is_some_and(|min| buf.len() < min)
+            && !matches!(self.queue.front(), Some(ReadItem::Closed))
+        {
+            let e = MockError(ErrorKind::InvalidInput);
+            self.history.push(Op::Error(e.kind()));
+            return Err(e);
+        }
+
+        let Some(next_item) = self.pop_item() else {
+            match &self.phase {
+                Some(phase) => panic!(
+                    "The caller tried to read data, but the Source is completely consumed (exhausted during phase `{}`)",
+                    phase
+                ),
+                None => panic!("The caller tried to read data, but the Source is completely consumed"),
+            }
+        };
 
-        match next_item {
+        let (res, op) = match next_item {
             ReadItem::Data(data) => {
                 let n = buf.len().min(data.len());
                 let (to_send, to_pend) = data.split_at(n);
 
                 // If we can't send all the data to the caller, put some back in the queue
                 if !to_pend.is_empty() {
-                    self.queue.push_front(ReadItem::Data(Vec::from(to_pend)));
+                    if n > 0 {
+                        self.split_reads
+                            .push((self.requested_reads.len() - 1, to_pend.len()));
+                    }
+                    self.push_partial(ReadItem::Data(Vec::from(to_pend)));
                 }
 
                 buf[0..n].copy_from_slice(to_send);
-                Ok(n)
+                (Ok(n), Op::Data(n))
+            }
+            ReadItem::Error(e) => (Err(e), Op::Error(e.kind())),
+            ReadItem::Closed => (Ok(0), Op::Closed),
+            ReadItem::NotReady
+            | ReadItem::Delay(_)
+            | ReadItem::Phase(_)
+            | ReadItem::Reset
+            | ReadItem::WaitUntil(_) => {
+                unreachable!("Markers are skipped before reading")
+            }
+            #[cfg(feature = "mmap")]
+            ReadItem::Mapped(map, offset) => {
+                let n = buf.len().min(map.len() - offset);
+                buf[0..n].copy_from_slice(&map[offset..offset + n]);
+
+                // If we can't send all the data to the caller, advance the offset for next time
+                if offset + n < map.len() {
+                    if n > 0 {
+                        self.split_reads
+                            .push((self.requested_reads.len() - 1, map.len() - offset - n));
+                    }
+                    self.push_partial(ReadItem::Mapped(map, offset + n));
+                }
+
+                (Ok(n), Op::Data(n))
+            }
+        };
+
+        self.history.push(op);
+        if let Ok(n) = res {
+            self.last_read_len = Some(n);
+            self.bytes_used += n;
+            if let Some(delivered) = &mut self.delivered {
+                delivered.extend_from_slice(&buf[0..n]);
+            }
+        }
+        if let (Op::Data(n @ 1..), Some((rng, probability))) = (op, &mut self.duplication) {
+            if self.duplicate_remaining > 0 {
+                self.duplicate_remaining -= n;
+            } else if rng.next_f64() < *probability {
+                self.push_item_front(ReadItem::Data(buf[0..n].to_vec()));
+                self.duplicate_remaining = n;
+            }
+        }
+        res
+    }
+}
+
+impl Source {
+    /// Perform an async read from the `Source` in `mock`. Everything which can pend is awaited
+    /// without holding any lock on the `Source`, which is only locked for the synchronous steps.
+    async fn read_async(
+        mock: &mut impl shared::WithMock<Self>,
+        buf: &mut [u8],
+    ) -> Result<usize, MockError> {
+        loop {
+            // Lock the channel before polling if the next item must come from it
+            #[cfg(feature = "tokio")]
+            let mut rx = match mock.with(|source| {
+                source.skip_markers();
+                source.channel.clone().filter(|_| source.queue.is_empty())
+            }) {
+                Some(rx) => Some(rx.lock_owned().await),
+                None => None,
+            };
+
+            let res = std::future::poll_fn(|cx| {
+                mock.with(|source| {
+                    source.poll_read(
+                        cx,
+                        #[cfg(feature = "tokio")]
+                        rx.as_deref_mut(),
+                        buf,
+                    )
+                })
+            })
+            .await;
+            if let Some(res) = res {
+                return res;
+            }
+        }
+    }
+
+    /// Poll an async read into `buf`, pending while delivery is paused, while waiting for the
+    /// clock, or while the channel or stream feeding the `Source` has no item ready. `rx` is the locked channel receiver, if it
+    /// was locked before polling. An item is queued in the same poll which takes it, so dropping
+    /// the read future never loses an item.
+    ///
+    /// This returns `None` if the next item must come from the channel, but it wasn't locked.
+    fn poll_read(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+        #[cfg(feature = "tokio")] rx: Option<&mut tokio::sync::mpsc::Receiver<Vec<u8>>>,
+        buf: &mut [u8],
+    ) -> std::task::Poll<Option<Result<usize, MockError>>> {
+        use std::task::Poll;
+
+        if let Some(pause) = &self.pause {
+            if pause.poll_resumed(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+        self.skip_markers();
+
+        // Once the clock reaches a wait, it is skipped along with any markers after it
+        while let Some(ReadItem::WaitUntil(at)) = self.queue.front() {
+            if self.wait_clock().poll_reached(*at, cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.skip_markers();
+        }
+
+        #[cfg(feature = "tokio")]
+        if self.channel.is_some() && self.queue.is_empty() {
+            let Some(rx) = rx else {
+                return Poll::Ready(None);
+            };
+            match rx.poll_recv(cx) {
+                Poll::Ready(item) => self
+                    .queue
+                    .push_back(item.map_or(ReadItem::Closed, ReadItem::Data)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        #[cfg(feature = "stream")]
+        if let Some(stream) = self.stream.as_ref().filter(|_| self.queue.is_empty()) {
+            match stream.poll_next(cx) {
+                Poll::Ready(item) => self.queue.push_back(ReadItem::from_stream_item(item)),
+                Poll::Pending => return Poll::Pending,
             }
-            ReadItem::Error(e) => Err(e),
-            ReadItem::Closed => Ok(0),
         }
+
+        Poll::Ready(Some(self.read_item(buf)))
     }
 }
 
 impl embedded_io_async::Read for Source {
     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
-        embedded_io::Read::read(self, buf)
+        Self::read_async(self, buf).await
     }
 }
 
-impl embedded_io::Write for Sink {
-    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+impl embedded_io::ReadReady for Source {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        // Markers which a read skips past don't decide readiness, but delays among them still
+        // advance the clock before any wait after them is checked
+        let mut delayed = Duration::ZERO;
+        let is_marker = |item: &ReadItem| {
+            matches!(
+                item,
+                ReadItem::Phase(_) | ReadItem::Reset | ReadItem::Delay(_)
+            )
+        };
+
+        // Queue the next item from the stream if it already has one ready
+        #[cfg(feature = "stream")]
+        if let Some(stream) = self
+            .stream
+            .as_ref()
+            .filter(|_| self.queue.iter().all(is_marker))
+        {
+            let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+            if let std::task::Poll::Ready(item) = stream.poll_next(&mut cx) {
+                self.queue.push_back(ReadItem::from_stream_item(item));
+            }
+        }
+
+        let next = self.queue.iter().find(|item| {
+            if let ReadItem::Delay(d) = item {
+                delayed += *d;
+            }
+            !is_marker(item)
+        });
+        let ready = match next {
+            Some(ReadItem::NotReady) => false,
+            Some(ReadItem::WaitUntil(at)) => !self.is_waiting(at.saturating_sub(delayed)),
+            Some(_) => true,
+            None if self.script.is_some() => true,
+            #[cfg(feature = "tokio")]
+            None if self.channel.is_some() => self
+                .channel
+                .as_ref()
+                .and_then(|rx| rx.try_lock().ok())
+                .is_some_and(|rx| !rx.is_empty() || rx.is_closed()),
+            None => false,
+        };
+        self.history.push(Op::ReadyCheck);
+        Ok(ready)
+    }
+}
+
+impl Sink {
+    /// Run the checks which happen before a write reaches the queue. This returns the length of
+    /// the caller's buffer to offer to the queue, or breaks with the result of a rejected write.
+    fn begin_write(&mut self, buf: &[u8]) -> ControlFlow<Result<usize, MockError>, usize> {
+        if let Some(max) = self.max_write_len {
+            assert!(
+                buf.len() <= max,
+                "The caller tried to write {} bytes, but writes are limited to {} bytes",
+                buf.len(),
+                max
+            );
+        }
+        self.check_write_discipline();
+
+        if let Some(e) = self.error_predicate.as_ref().and_then(|pred| (pred.0)(buf)) {
+            return ControlFlow::Break(self.reject_write(e));
+        }
+
+        // A reset discards the data accepted since the last flush
+        while let Some(WriteItem::Reset) = self.queue.front() {
+            self.queue.pop_front();
+            self.data
+                .truncate(self.data.len() - (self.unflushed_len - self.staged.len()));
+            self.staged.clear();
+            self.unflushed_len = 0;
+            self.history.push(Op::Reset);
+        }
+
+        // Only offer as much of the buffer as the backpressure curve allows
+        let len = match &self.backpressure {
+            Some(curve) => buf.len().min((curve.0.lock().unwrap())(self.unflushed_len)),
+            None => buf.len(),
+        };
+        if len == 0 && !buf.is_empty() {
+            // Nothing is offered, so the queued items are left untouched
+            self.write_ops.push(self.history.len());
+            self.history.push(Op::Data(0));
+            self.last_write_len = Some(0);
+            return ControlFlow::Break(Ok(0));
+        }
+
+        // Data sent to a channel must not overrun the flush threshold, as it can't be taken back
+        #[cfg(feature = "tokio")]
+        if self.writes_to_channel() && self.would_overrun_flush_threshold(len) {
+            return ControlFlow::Break(self.reject_write(MockError(ErrorKind::OutOfMemory)));
+        }
+
+        ControlFlow::Continue(len)
+    }
+
+    /// Record a write which was rejected with `e` before reaching the queue
+    fn reject_write(&mut self, e: MockError) -> Result<usize, MockError> {
+        self.write_ops.push(self.history.len());
+        self.history.push(Op::Error(e.kind()));
+        Err(e)
+    }
+
+    /// Whether the next write should be sent to the channel feeding the `Sink`
+    #[cfg(feature = "tokio")]
+    fn writes_to_channel(&self) -> bool {
+        self.channel.is_some() && self.queue.is_empty()
+    }
+
+    /// Queue the item for a write of `len` bytes which was sent to the channel, given whether the
+    /// send succeeded
+    #[cfg(feature = "tokio")]
+    fn queue_channel_write(&mut self, len: usize, sent: bool) {
+        self.queue.push_back(match sent {
+            true => WriteItem::AcceptData(len),
+            false => WriteItem::Closed,
+        });
+    }
+
+    /// Record `bytes` accepted by a write, holding them back until they are drained if flushes
+    /// drain incrementally
+    fn accept(&mut self, bytes: &[u8]) {
+        match self.flush_drain_len {
+            Some(_) => self.staged.extend_from_slice(bytes),
+            None => self.data.extend_from_slice(bytes),
+        }
+        self.unflushed_len += bytes.len();
+    }
+
+    /// Write `buf` against the next item in the queue, after the checks in
+    /// [`begin_write`](Self::begin_write) have passed
+    fn finish_write(&mut self, buf: &[u8]) -> Result<usize, MockError> {
         let next_chunk = self
             .queue
             .pop_front()
             .expect("The caller tried to write data, but the Sink is completely consumed");
 
-        match next_chunk {
+        let (res, op) = match next_chunk {
             WriteItem::AcceptData(maxsize) => {
                 let n = buf.len().min(maxsize);
-                let remaining = maxsize - n;
 
-                // If the max size wasn't written, push the remaining length back to the queue
-                if remaining > 0 {
-                    self.queue.push_front(WriteItem::AcceptData(remaining));
+                if self.would_overrun_flush_threshold(n) {
+                    // This write would overrun the flush threshold, so leave the item in the queue
+                    self.queue.push_front(WriteItem::AcceptData(maxsize));
+                    let e = MockError(ErrorKind::OutOfMemory);
+                    (Err(e), Op::Error(e.kind()))
+                } else {
+                    let remaining = maxsize - n;
+
+                    // If the max size wasn't written, push the remaining length back to the queue
+                    if remaining > 0 {
+                        self.queue.push_front(WriteItem::AcceptData(remaining));
+                    }
+
+                    self.accept(&buf[0..n]);
+                    (Ok(n), Op::Data(n))
+                }
+            }
+            WriteItem::AcceptUntil {
+                sentinel,
+                mut tail,
+                then,
+            } => {
+                // Find the end of the sentinel, including any part of it seen in previous writes
+                let seen = tail.len();
+                tail.extend_from_slice(buf);
+                let end = (seen..tail.len()).find(|&i| tail[..=i].ends_with(&sentinel));
+                let n = end.map_or(buf.len(), |i| i + 1 - seen);
+
+                if self.would_overrun_flush_threshold(n) {
+                    // This write would overrun the flush threshold, so leave the item in the queue
+                    tail.truncate(seen);
+                    self.queue.push_front(WriteItem::AcceptUntil {
+                        sentinel,
+                        tail,
+                        then,
+                    });
+                    let e = MockError(ErrorKind::OutOfMemory);
+                    (Err(e), Op::Error(e.kind()))
+                } else {
+                    if end.is_some() {
+                        self.queue.push_front(match then {
+                            AfterSentinel::Close => WriteItem::Closed,
+                            AfterSentinel::Error(e) => WriteItem::Error(e),
+                        });
+                    } else {
+                        // Only keep enough of the data to complete a sentinel on the next write
+                        tail.drain(..tail.len().saturating_sub(sentinel.len() - 1));
+                        self.queue.push_front(WriteItem::AcceptUntil {
+                            sentinel,
+                            tail,
+                            then,
+                        });
+                    }
+
+                    self.accept(&buf[0..n]);
+                    (Ok(n), Op::Data(n))
                 }
+            }
+            WriteItem::ExpectData(expected) => {
+                let n = buf.len().min(expected.len());
 
-                self.data.extend_from_slice(buf);
-                Ok(n)
+                if self.would_overrun_flush_threshold(n) {
+                    // This write would overrun the flush threshold, so leave the item in the queue
+                    self.queue.push_front(WriteItem::ExpectData(expected));
+                    let e = MockError(ErrorKind::OutOfMemory);
+                    (Err(e), Op::Error(e.kind()))
+                } else {
+                    if let Some(i) = (0..n).find(|&i| buf[i] != expected[i]) {
+                        panic!(
+                            "The caller wrote {:#04x} at offset {} of an expected write, but {:#04x} was expected\n{}",
+                            buf[i],
+                            i,
+                            expected[i],
+                            byte_diff(&expected[0..n], &buf[0..n])
+                        );
+                    }
+
+                    // If not all of the expected data was written, expect the rest next
+                    if n < expected.len() {
+                        self.queue
+                            .push_front(WriteItem::ExpectData(expected[n..].to_vec()));
+                    }
+
+                    self.accept(&buf[0..n]);
+                    (Ok(n), Op::Data(n))
+                }
             }
-            WriteItem::Error(e) => Err(e),
-            WriteItem::Closed => Ok(0),
+            WriteItem::Error(e) => (Err(e), Op::Error(e.kind())),
+            WriteItem::Closed => (Ok(0), Op::Closed),
+            WriteItem::Reset => unreachable!("Resets are applied before writing"),
+        };
+
+        if let (Op::Data(_), Some(clock)) = (op, &self.clock) {
+            self.write_times.push(clock.now());
+        }
+        self.write_ops.push(self.history.len());
+        self.history.push(op);
+        self.peak_unflushed_len = self.peak_unflushed_len.max(self.unflushed_len);
+        if let Ok(n) = res {
+            self.last_write_len = Some(n);
+        }
+        res
+    }
+}
+
+impl embedded_io::Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if let Some(pause) = &self.pause {
+            pause.assert_resumed("write");
+        }
+        let buf = match self.begin_write(buf) {
+            ControlFlow::Continue(len) => &buf[0..len],
+            ControlFlow::Break(res) => return res,
+        };
+
+        #[cfg(feature = "tokio")]
+        if self.writes_to_channel() {
+            let sent = self
+                .channel
+                .as_ref()
+                .is_some_and(|tx| tx.blocking_send(buf.to_vec()).is_ok());
+            self.queue_channel_write(buf.len(), sent);
         }
+
+        self.finish_write(buf)
     }
 
     fn flush(&mut self) -> Result<(), Self::Error> {
+        self.check_flush_discipline();
+
+        if let Some((at, clock)) = self.flush_wait() {
+            if clock.now() < at {
+                let e = MockError(WOULD_BLOCK);
+                self.history.push(Op::Error(e.kind()));
+                return Err(e);
+            }
+        }
+
+        if let Some(Err(e)) = self.flush_queue.pop_front() {
+            self.history.push(Op::Error(e.kind()));
+            return Err(e);
+        }
+
+        self.history.push(Op::Flush);
+        let drained = self
+            .flush_drain_len
+            .map_or(self.unflushed_len, |n| n.min(self.unflushed_len));
+        self.unflushed_len -= drained;
+        self.data
+            .extend(self.staged.drain(..drained.min(self.staged.len())));
         Ok(())
     }
 }
 
+impl Sink {
+    /// Perform an async write to the `Sink` in `mock`. Everything which can pend is awaited
+    /// without holding any lock on the `Sink`, which is only locked for the synchronous steps.
+    async fn write_async(
+        mock: &mut impl shared::WithMock<Self>,
+        buf: &[u8],
+    ) -> Result<usize, MockError> {
+        if let Some(pause) = mock.with(|sink| sink.pause.clone()) {
+            pause.wait().await;
+        }
+
+        let buf = match mock.with(|sink| sink.begin_write(buf)) {
+            ControlFlow::Continue(len) => &buf[0..len],
+            ControlFlow::Break(res) => return res,
+        };
+
+        #[cfg(feature = "tokio")]
+        if let Some(tx) =
+            mock.with(|sink| sink.channel.clone().filter(|_| sink.writes_to_channel()))
+        {
+            let sent = tx.send(buf.to_vec()).await.is_ok();
+            return mock.with(|sink| {
+                sink.queue_channel_write(buf.len(), sent);
+                sink.finish_write(buf)
+            });
+        }
+
+        mock.with(|sink| sink.finish_write(buf))
+    }
+
+    /// Perform an async flush of the `Sink` in `mock`, pending without holding any lock on the
+    /// `Sink` until the clock reaches the time set with
+    /// [`flush_not_before`](Self::flush_not_before)
+    async fn flush_async(mock: &mut impl shared::WithMock<Self>) -> Result<(), MockError> {
+        if let Some((at, clock)) = mock.with(|sink| sink.flush_wait()) {
+            std::future::poll_fn(|cx| clock.poll_reached(at, cx)).await;
+        }
+        mock.with(embedded_io::Write::flush)
+    }
+
+    /// Get the time set with [`flush_not_before`](Self::flush_not_before), if any, and the clock
+    /// it is measured by
+    fn flush_wait(&self) -> Option<(Duration, MockClock)> {
+        let at = self.flush_not_before?;
+        let clock = self
+            .clock
+            .clone()
+            .expect("The caller tried to flush before a time, but no clock is set on the Sink");
+        Some((at, clock))
+    }
+}
+
 impl embedded_io_async::Write for Sink {
     async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
-        embedded_io::Write::write(self, buf)
+        Self::write_async(self, buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Self::flush_async(self).await
+    }
+}
+
+impl embedded_io::WriteReady for Sink {
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        let ready = match self.queue.front() {
+            Some(_) => true,
+            #[cfg(feature = "tokio")]
+            None if self.channel.is_some() => self
+                .channel
+                .as_ref()
+                .is_some_and(|tx| tx.capacity() > 0 || tx.is_closed()),
+            None => false,
+        };
+        self.history.push(Op::ReadyCheck);
+        Ok(ready)
     }
 }
 
@@ -425,6 +3922,10 @@ impl<T: embedded_io_async::Write> embedded_io_async::Write for OwnedHandle<'_, T
     async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
         self.inner.write(buf).await
     }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush().await
+    }
 }
 
 impl<T: embedded_io::Read> embedded_io::Read for OwnedHandle<'_, T> {
@@ -433,6 +3934,18 @@ impl<T: embedded_io::Read> embedded_io::Read for OwnedHandle<'_, T> {
     }
 }
 
+impl<T: embedded_io::ReadReady> embedded_io::ReadReady for OwnedHandle<'_, T> {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        self.inner.read_ready()
+    }
+}
+
+impl<T: embedded_io::WriteReady> embedded_io::WriteReady for OwnedHandle<'_, T> {
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        self.inner.write_ready()
+    }
+}
+
 impl<T: embedded_io_async::Read> embedded_io_async::Read for OwnedHandle<'_, T> {
     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
         self.inner.read(buf).await
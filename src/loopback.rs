@@ -0,0 +1,134 @@
+use crate::{MockError, OwnedHandle};
+use embedded_io::ErrorType;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// A transform applied to the data of each write to a [`Loopback`]
+type Transform = Box<dyn FnMut(&[u8]) -> Vec<u8>>;
+
+/// A mock which makes data written to it available to be read back.
+///
+/// By default the data is read back unchanged, but a transform can be applied to each write with
+/// [`Loopback::with_transform`] to model a device which responds to commands with processed data.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::Loopback;
+/// use embedded_io::{Read, Write};
+///
+/// let mut loopback = Loopback::new();
+///
+/// let res = loopback.write_all("hello".as_bytes());
+/// assert!(res.is_ok());
+///
+/// let mut buf: [u8; 64] = [0; 64];
+/// let res = loopback.read(&mut buf);
+/// assert!(res.is_ok_and(|n| &buf[0..n] == "hello".as_bytes()));
+/// ```
+#[derive(Default)]
+pub struct Loopback {
+    /// Data which has been written but not yet read
+    buffer: VecDeque<u8>,
+
+    /// A transform applied to each write before the data becomes readable
+    transform: Option<Transform>,
+}
+
+impl Loopback {
+    /// Create a new empty Loopback
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new empty Loopback which applies `f` to the data of each write. The output of `f`
+    /// is queued for reading.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use mock_embedded_io::Loopback;
+    /// use embedded_io::{Read, Write};
+    ///
+    /// let mut loopback = Loopback::with_transform(|data| data.to_ascii_uppercase());
+    ///
+    /// let res = loopback.write("hello".as_bytes());
+    /// assert!(res.is_ok_and(|n| n == 5));
+    ///
+    /// let mut buf: [u8; 64] = [0; 64];
+    /// let res = loopback.read(&mut buf);
+    /// assert!(res.is_ok_and(|n| &buf[0..n] == "HELLO".as_bytes()));
+    /// ```
+    pub fn with_transform<F: FnMut(&[u8]) -> Vec<u8> + 'static>(f: F) -> Self {
+        Self {
+            buffer: VecDeque::new(),
+            transform: Some(Box::new(f)),
+        }
+    }
+
+    /// Get the number of bytes which are available to be read
+    pub fn available(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Get an [`OwnedHandle`] containing the `Loopback`
+    pub fn owned_handle(&mut self) -> OwnedHandle<'_, Self> {
+        OwnedHandle { inner: self }
+    }
+}
+
+impl fmt::Debug for Loopback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Loopback")
+            .field("buffer", &self.buffer)
+            .field("transform", &self.transform.is_some())
+            .finish()
+    }
+}
+
+impl ErrorType for Loopback {
+    type Error = MockError;
+}
+
+impl embedded_io::Read for Loopback {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        assert!(
+            buf.is_empty() || !self.buffer.is_empty(),
+            "The caller tried to read data, but nothing has been written to the Loopback"
+        );
+
+        let n = buf.len().min(self.buffer.len());
+        for (dst, src) in buf.iter_mut().zip(self.buffer.drain(0..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+}
+
+impl embedded_io_async::Read for Loopback {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        embedded_io::Read::read(self, buf)
+    }
+}
+
+impl embedded_io::Write for Loopback {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        match &mut self.transform {
+            Some(transform) => self.buffer.extend(transform(buf)),
+            None => self.buffer.extend(buf),
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl embedded_io_async::Write for Loopback {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        embedded_io::Write::write(self, buf)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        embedded_io::Write::flush(self)
+    }
+}
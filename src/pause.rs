@@ -0,0 +1,110 @@
+use std::future::poll_fn;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// The state shared between clones of a [`PauseController`]
+#[derive(Debug, Default)]
+struct PauseState {
+    /// Whether delivery is currently paused
+    paused: bool,
+
+    /// Wakers of the futures which pended while delivery was paused
+    wakers: Vec<Waker>,
+}
+
+/// A handle to pause and resume delivery on the mocks it is attached to.
+///
+/// Attach a controller to a [`Source`](crate::Source) with
+/// [`Source::pause_controller`](crate::Source::pause_controller) or to a
+/// [`Sink`](crate::Sink) with [`Sink::pause_controller`](crate::Sink::pause_controller). While it
+/// is paused, async reads and writes on those mocks pend without consuming anything, and
+/// [`resume`](Self::resume) wakes them. Clones of a `PauseController` share the same state, so one
+/// clone can be attached to the mocks while the test keeps another.
+///
+/// Blocking reads and writes can't pend, so calling them while paused panics.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::{PauseController, Source};
+/// # #[tokio::main]
+/// # async fn main() {
+/// use embedded_io_async::Read;
+///
+/// let controller = PauseController::new();
+/// let mut mock_source = Source::new()
+///                         .data("hello".as_bytes())
+///                         .pause_controller(controller.clone());
+///
+/// controller.pause();
+/// let reader = tokio::spawn(async move {
+///     let mut buf: [u8; 64] = [0; 64];
+///     mock_source.read(&mut buf).await
+/// });
+///
+/// // The read is suspended until delivery is resumed
+/// tokio::task::yield_now().await;
+/// assert!(!reader.is_finished());
+///
+/// controller.resume();
+/// assert_eq!(reader.await.unwrap(), Ok(5));
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PauseController {
+    /// The shared pause state
+    state: Arc<Mutex<PauseState>>,
+}
+
+impl PauseController {
+    /// Create a new controller, starting resumed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pause delivery, so subsequent async reads and writes pend
+    pub fn pause(&self) {
+        self.state.lock().unwrap().paused = true;
+    }
+
+    /// Resume delivery, waking any reads and writes which pended while paused
+    pub fn resume(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = false;
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// Whether delivery is currently paused
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    /// Pend until delivery is resumed
+    pub(crate) async fn wait(&self) {
+        poll_fn(|cx| self.poll_resumed(cx)).await
+    }
+
+    /// Check whether delivery is resumed, registering the task in `cx` to be woken if it isn't
+    pub(crate) fn poll_resumed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.paused {
+            // Only keep one waker for each waiting task, however often it is polled
+            if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                state.wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    /// Panic if delivery is paused, as a blocking `operation` can't pend
+    pub(crate) fn assert_resumed(&self, operation: &str) {
+        assert!(
+            !self.is_paused(),
+            "The caller tried to {} in blocking mode while delivery was paused, but only async operations can wait to be resumed",
+            operation
+        );
+    }
+}
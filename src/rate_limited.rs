@@ -0,0 +1,141 @@
+use crate::{MockClock, MockError, WOULD_BLOCK};
+use embedded_io::{Error, ErrorType};
+use std::time::Duration;
+
+/// An adapter which limits the number of operations on a `Read` or `Write` per window of time.
+///
+/// Time is measured with a [`MockClock`] and divided into consecutive windows of a fixed length,
+/// starting from zero. Once the limit has been reached within the current window, further reads
+/// and writes return [`ErrorKind::Interrupted`] without reaching the inner transport, until the
+/// clock is advanced into the next window. Errors from the inner transport are returned as a
+/// [`MockError`] of the same kind.
+///
+/// [`ErrorKind::Interrupted`]: embedded_io::ErrorKind::Interrupted
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::{MockClock, MockError, RateLimited, Source};
+/// use embedded_io::{ErrorKind, Read};
+/// use std::time::Duration;
+///
+/// let mut mock_source = Source::new().data("abc");
+/// let clock = MockClock::new();
+/// let mut limited = RateLimited::new(
+///     mock_source.owned_handle(),
+///     2,
+///     Duration::from_millis(10),
+///     clock.clone(),
+/// );
+///
+/// let mut buf: [u8; 1] = [0; 1];
+/// assert_eq!(limited.read(&mut buf), Ok(1));
+/// assert_eq!(limited.read(&mut buf), Ok(1));
+/// assert_eq!(limited.read(&mut buf), Err(MockError(ErrorKind::Interrupted)));
+///
+/// clock.advance(Duration::from_millis(10));
+/// assert_eq!(limited.read(&mut buf), Ok(1));
+/// ```
+#[derive(Debug)]
+pub struct RateLimited<T> {
+    /// The wrapped transport
+    inner: T,
+
+    /// The maximum number of operations allowed in each window
+    ops_per_window: usize,
+
+    /// The length of each window
+    window: Duration,
+
+    /// The clock used to determine the current window
+    clock: MockClock,
+
+    /// The index of the window in which operations were last counted
+    current_window: u128,
+
+    /// The number of operations performed in the current window
+    ops_in_window: usize,
+}
+
+impl<T> RateLimited<T> {
+    /// Wrap `inner`, allowing at most `ops_per_window` reads or writes in each `window` of time
+    /// as measured by `clock`.
+    pub fn new(inner: T, ops_per_window: usize, window: Duration, clock: MockClock) -> Self {
+        assert!(!window.is_zero(), "The rate limit window must be non-zero");
+
+        Self {
+            inner,
+            ops_per_window,
+            window,
+            clock,
+            current_window: 0,
+            ops_in_window: 0,
+        }
+    }
+
+    /// Get a reference to the wrapped transport
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Consume the adapter, returning the wrapped transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Count an operation against the current window, or return an error if the limit has been
+    /// reached
+    fn take_op(&mut self) -> Result<(), MockError> {
+        let window = self.clock.now().as_nanos() / self.window.as_nanos();
+        if window != self.current_window {
+            self.current_window = window;
+            self.ops_in_window = 0;
+        }
+
+        if self.ops_in_window >= self.ops_per_window {
+            return Err(MockError(WOULD_BLOCK));
+        }
+
+        self.ops_in_window += 1;
+        Ok(())
+    }
+}
+
+impl<T> ErrorType for RateLimited<T> {
+    type Error = MockError;
+}
+
+impl<T: embedded_io::Read> embedded_io::Read for RateLimited<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.take_op()?;
+        self.inner.read(buf).map_err(|e| MockError(e.kind()))
+    }
+}
+
+impl<T: embedded_io_async::Read> embedded_io_async::Read for RateLimited<T> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.take_op()?;
+        self.inner.read(buf).await.map_err(|e| MockError(e.kind()))
+    }
+}
+
+impl<T: embedded_io::Write> embedded_io::Write for RateLimited<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.take_op()?;
+        self.inner.write(buf).map_err(|e| MockError(e.kind()))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush().map_err(|e| MockError(e.kind()))
+    }
+}
+
+impl<T: embedded_io_async::Write> embedded_io_async::Write for RateLimited<T> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.take_op()?;
+        self.inner.write(buf).await.map_err(|e| MockError(e.kind()))
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush().await.map_err(|e| MockError(e.kind()))
+    }
+}
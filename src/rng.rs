@@ -0,0 +1,59 @@
+use std::ops::Range;
+
+/// A small deterministic pseudo-random number generator.
+///
+/// This is not suitable for cryptography, but given the same seed it always produces the same
+/// sequence of values, so a failing randomised test case can be reproduced from its seed.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::Rng;
+/// let mut a = Rng::new(42);
+/// let mut b = Rng::new(42);
+/// assert_eq!(a.next_u64(), b.next_u64());
+///
+/// let n = a.range(10..20);
+/// assert!((10..20).contains(&n));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rng {
+    /// The internal state of the generator
+    state: u64,
+}
+
+impl Rng {
+    /// Create a new generator from `seed`
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Get the next random `u64`
+    pub fn next_u64(&mut self) -> u64 {
+        // SplitMix64
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Get a random value within `range`, which must not be empty
+    pub fn range(&mut self, range: Range<usize>) -> usize {
+        assert!(!range.is_empty(), "The range must not be empty");
+        let len = (range.end - range.start) as u64;
+        range.start + (self.next_u64() % len) as usize
+    }
+
+    /// Get a random value between 0 (inclusive) and 1 (exclusive)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Fill `buf` with random bytes
+    pub fn fill_bytes(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[0..chunk.len()]);
+        }
+    }
+}
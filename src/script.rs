@@ -0,0 +1,51 @@
+use crate::MockError;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// The next item for a scripted [`Source`](crate::Source) to deliver, produced by a
+/// [`ReadScript`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadOutcome {
+    /// Yield data to the caller. This can be read in one chunk or incrementally, as with
+    /// [`Source::data`](crate::Source::data).
+    Data(Vec<u8>),
+
+    /// Return an error to the caller
+    Error(MockError),
+
+    /// Return a data length of zero to the caller
+    Closed,
+}
+
+/// A programmable script which produces the items delivered by a [`Source`](crate::Source) on
+/// demand, set with [`Source::from_script_impl`](crate::Source::from_script_impl).
+///
+/// This allows each item to depend on the state of the test at the time it is read, rather than
+/// being fixed when the `Source` is built.
+pub trait ReadScript: Send {
+    /// Produce the next item to deliver. This is called each time the `Source` is read with no
+    /// items left to deliver.
+    fn next_item(&mut self) -> ReadOutcome;
+}
+
+/// A [`ReadScript`] feeding a `Source`, shared between clones of the `Source`
+#[derive(Clone)]
+pub(crate) struct SharedScript(Arc<Mutex<Box<dyn ReadScript>>>);
+
+impl SharedScript {
+    /// Share `script` between clones of a `Source`
+    pub(crate) fn new(script: Box<dyn ReadScript>) -> Self {
+        Self(Arc::new(Mutex::new(script)))
+    }
+
+    /// Produce the next item from the script
+    pub(crate) fn next_item(&self) -> ReadOutcome {
+        self.0.lock().unwrap().next_item()
+    }
+}
+
+impl fmt::Debug for SharedScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedScript").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{MockError, Sink, Source};
+use embedded_io::ErrorType;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A [`Source`] which can be shared between threads, for example across benchmark iterations.
+///
+/// Clones of a `SharedSource` refer to the same underlying `Source`, which is protected by a
+/// mutex. Each read locks the mutex once for the duration of the call, so reads from different
+/// threads never interleave within a single call. The async `Read` implementation waits for a
+/// paused controller, the clock, a channel or a stream without holding the lock, and only locks
+/// the mutex to perform the read, so the lock is never held across an await point.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::{SharedSource, Source};
+/// use embedded_io::Read;
+///
+/// let shared = SharedSource::new(Source::new().data([0; 4000]));
+///
+/// let handles: Vec<_> = (0..4)
+///     .map(|_| {
+///         let mut source = shared.clone();
+///         std::thread::spawn(move || {
+///             let mut buf: [u8; 10] = [0; 10];
+///             for _ in 0..100 {
+///                 assert_eq!(source.read(&mut buf), Ok(10));
+///             }
+///         })
+///     })
+///     .collect();
+///
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// assert!(shared.lock().is_consumed());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedSource {
+    /// The shared `Source`
+    inner: Arc<Mutex<Source>>,
+}
+
+impl SharedSource {
+    /// Share `source` between threads
+    pub fn new(source: Source) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(source)),
+        }
+    }
+
+    /// Lock the underlying `Source`, to inspect it
+    pub fn lock(&self) -> MutexGuard<'_, Source> {
+        self.inner.lock().unwrap()
+    }
+}
+
+/// A [`Sink`] which can be shared between threads, for example across benchmark iterations.
+///
+/// Clones of a `SharedSink` refer to the same underlying `Sink`, which is protected by a mutex.
+/// Each write or flush locks the mutex once for the duration of the call, so the data of a single
+/// write is always recorded contiguously. The async `Write` implementation waits for a paused
+/// controller, the clock or a channel without holding the lock, and only locks the mutex to
+/// perform the write or flush, so the lock is never held across an await point.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::{SharedSink, Sink};
+/// use embedded_io::Write;
+///
+/// let shared = SharedSink::new(Sink::new().accept_data(4000));
+///
+/// let handles: Vec<_> = (0..4)
+///     .map(|_| {
+///         let mut sink = shared.clone();
+///         std::thread::spawn(move || {
+///             for _ in 0..100 {
+///                 assert!(sink.write_all(&[0; 10]).is_ok());
+///             }
+///         })
+///     })
+///     .collect();
+///
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// assert_eq!(shared.take_data().len(), 4000);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedSink {
+    /// The shared `Sink`
+    inner: Arc<Mutex<Sink>>,
+}
+
+impl SharedSink {
+    /// Share `sink` between threads
+    pub fn new(sink: Sink) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(sink)),
+        }
+    }
+
+    /// Lock the underlying `Sink`, to inspect it
+    pub fn lock(&self) -> MutexGuard<'_, Sink> {
+        self.inner.lock().unwrap()
+    }
+
+    /// Take the data which has been received from the writers so far, leaving the recorded data
+    /// empty. The taken data counts as flushed, so a later device reset can't discard it.
+    pub fn take_data(&self) -> Vec<u8> {
+        let mut sink = self.lock();
+        // Only bytes held back until a flush drains them are still unflushed
+        sink.unflushed_len = sink.staged.len();
+        std::mem::take(&mut sink.data)
+    }
+}
+
+/// Access to a mock which may be shared behind a lock
+pub(crate) trait WithMock<T> {
+    /// Call `f` with the mock, holding any lock only for the duration of the call
+    fn with<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R;
+}
+
+impl<T> WithMock<T> for T {
+    fn with<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(self)
+    }
+}
+
+impl<T> WithMock<T> for Arc<Mutex<T>> {
+    fn with<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.lock().unwrap())
+    }
+}
+
+impl ErrorType for SharedSource {
+    type Error = MockError;
+}
+
+impl ErrorType for SharedSink {
+    type Error = MockError;
+}
+
+impl embedded_io::Read for SharedSource {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.lock().read(buf)
+    }
+}
+
+impl embedded_io_async::Read for SharedSource {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Source::read_async(&mut self.inner, buf).await
+    }
+}
+
+impl embedded_io::Write for SharedSink {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.lock().flush()
+    }
+}
+
+impl embedded_io_async::Write for SharedSink {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Sink::write_async(&mut self.inner, buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Sink::flush_async(&mut self.inner).await
+    }
+}
@@ -0,0 +1,46 @@
+use crate::MockError;
+use futures_core::Stream;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// A boxed stream of items for a [`Source`](crate::Source) to deliver
+type BoxedStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>, MockError>> + Send>>;
+
+/// A stream feeding a `Source`, shared between clones of the `Source`
+#[derive(Clone)]
+pub(crate) struct SharedStream(Arc<Mutex<Option<BoxedStream>>>);
+
+impl SharedStream {
+    /// Share `stream` between clones of a `Source`
+    pub(crate) fn new<S: Stream<Item = Result<Vec<u8>, MockError>> + Send + 'static>(
+        stream: S,
+    ) -> Self {
+        Self(Arc::new(Mutex::new(Some(Box::pin(stream)))))
+    }
+
+    /// Poll the stream for its next item. Once the stream has ended it is dropped, so it is never
+    /// polled again and this keeps returning `None`.
+    pub(crate) fn poll_next(
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Vec<u8>, MockError>>> {
+        let mut guard = self.0.lock().unwrap();
+        let Some(stream) = guard.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        let item = stream.as_mut().poll_next(cx);
+        if let Poll::Ready(None) = item {
+            *guard = None;
+        }
+        item
+    }
+}
+
+impl fmt::Debug for SharedStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedStream").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{MockClock, MockError, Sink, WriteItem};
+use embedded_io::{Error, ErrorType, Write};
+
+/// An adapter which forwards writes to the inner `Write`, while also recording them in a
+/// [`Sink`].
+///
+/// This is useful for exercising a real transport while still being able to inspect exactly what
+/// was sent afterwards. The inner transport decides how much of each write is accepted, and only
+/// the accepted bytes are recorded. The record is made by writing the accepted bytes to the
+/// `Sink`, so it tracks the staged length and write timestamps as a `Sink` written directly
+/// would.
+///
+/// ### Example
+/// ```rust
+/// # use mock_embedded_io::{Sink, Tee};
+/// use embedded_io::Write;
+///
+/// // A Sink stands in for a real transport here, which only accepts 3 bytes per write
+/// let transport = Sink::new().accept_data(3).accept_data(3);
+/// let mut tee = Tee::new(transport);
+///
+/// let res = tee.write("hello".as_bytes());
+/// assert_eq!(res, Ok(3));
+///
+/// let res = tee.write("lo".as_bytes());
+/// assert_eq!(res, Ok(2));
+///
+/// let (_transport, recorded) = tee.into_parts();
+/// assert_eq!(recorded.into_inner_data(), "hello".as_bytes());
+/// ```
+#[derive(Debug)]
+pub struct Tee<W> {
+    /// The wrapped transport
+    inner: W,
+
+    /// A record of the data accepted by the wrapped transport
+    recorded: Sink,
+}
+
+impl<W> Tee<W> {
+    /// Wrap `inner`, recording the data written to it
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            recorded: Sink::unlimited(),
+        }
+    }
+
+    /// Timestamp each recorded write with the current time of `clock`, as
+    /// [`Sink::clock`] does
+    pub fn clock(mut self, clock: MockClock) -> Self {
+        self.recorded = self.recorded.clock(clock);
+        self
+    }
+
+    /// Get a reference to the wrapped transport
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get the [`Sink`] holding a record of the data accepted by the wrapped transport
+    pub fn recorded(&self) -> &Sink {
+        &self.recorded
+    }
+
+    /// Consume the adapter, returning the wrapped transport and the [`Sink`] holding the record
+    /// of the data it accepted
+    pub fn into_parts(self) -> (W, Sink) {
+        (self.inner, self.recorded)
+    }
+
+    /// Record the result of a write to the wrapped transport
+    fn record_write<E: Error>(&mut self, buf: &[u8], res: &Result<usize, E>) {
+        // Make the recording Sink return the same result as the wrapped transport
+        match res {
+            Ok(0) if !buf.is_empty() => self.recorded.queue.push_front(WriteItem::Closed),
+            Ok(_) => (),
+            Err(e) => self
+                .recorded
+                .queue
+                .push_front(WriteItem::Error(MockError(e.kind()))),
+        }
+        let n = *res.as_ref().unwrap_or(&0);
+        let _ = self.recorded.write(&buf[0..n]);
+    }
+
+    /// Record the result of a flush of the wrapped transport
+    fn record_flush<E: Error>(&mut self, res: &Result<(), E>) {
+        if let Err(e) = res {
+            self.recorded
+                .flush_queue
+                .push_front(Err(MockError(e.kind())));
+        }
+        let _ = self.recorded.flush();
+    }
+}
+
+impl<W: ErrorType> ErrorType for Tee<W> {
+    type Error = W::Error;
+}
+
+impl<W: embedded_io::Write> embedded_io::Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let res = self.inner.write(buf);
+        self.record_write(buf, &res);
+        res
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        let res = self.inner.flush();
+        self.record_flush(&res);
+        res
+    }
+}
+
+impl<W: embedded_io_async::Write> embedded_io_async::Write for Tee<W> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let res = self.inner.write(buf).await;
+        self.record_write(buf, &res);
+        res
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        let res = self.inner.flush().await;
+        self.record_flush(&res);
+        res
+    }
+}